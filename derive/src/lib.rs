@@ -28,6 +28,18 @@ pub fn dyn_layout_macro(input: TokenStream) -> TokenStream {
         }
     };
 
+    // half precision and normalized types from `dyn_pod_struct::packed`
+    let packed_types = [
+        "f16",
+        "F16x2",
+        "F16x3",
+        "F16x4",
+        "Unorm8x4",
+        "Snorm8x4",
+        "Unorm16x2",
+        "Snorm16x2",
+    ];
+
     let basic_types: HashSet<String> = [
         "u8", "u16", "u32", "u64", "u128", "i8", "i16", "i32", "i64", "i128", "f32", "f64",
         // glam types (to avoid orphan rule issues with glam types)
@@ -36,6 +48,7 @@ pub fn dyn_layout_macro(input: TokenStream) -> TokenStream {
         "DAffine3",
     ]
     .iter()
+    .chain(&packed_types)
     .map(|s| s.to_string())
     .collect();
 
@@ -0,0 +1,194 @@
+use std::{
+    any::type_name,
+    fmt::{self, Display},
+};
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    base_type::{get_base_type, BaseType, IntoBaseType},
+    dyn_layout::DynLayout,
+    dyn_struct::{DynField, DynStruct},
+    tracked_dyn_struct::TrackedDynStruct,
+};
+
+/// Error returned by the checked accessors (`try_get`, `try_get_mut`, ...). Unlike `get` these also check the type
+/// of `T` in release builds, the `_raw` variants only check that it fits.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AccessError {
+    EmptyPath,
+    /// `segment` doesn't exist in the struct at `parent` (empty for the top level struct), or `parent` isn't a struct
+    /// or array.
+    PathNotFound {
+        parent: String,
+        segment: String,
+    },
+    /// The requested type isn't the type of the field
+    TypeMismatch {
+        path: String,
+        expected: BaseType,
+        requested: &'static str,
+    },
+    /// `size` bytes at `offset` don't fit in the `len` bytes of data
+    OutOfBounds {
+        offset: usize,
+        size: usize,
+        len: usize,
+    },
+    /// The data at `offset` isn't aligned for the requested type
+    Misaligned {
+        offset: usize,
+        align: usize,
+    },
+}
+
+impl Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessError::EmptyPath => write!(f, "empty path"),
+            AccessError::PathNotFound { parent, segment } if parent.is_empty() => {
+                write!(f, "no field `{segment}`")
+            }
+            AccessError::PathNotFound { parent, segment } => {
+                write!(f, "no field `{segment}` in `{parent}`")
+            }
+            AccessError::TypeMismatch {
+                path,
+                expected,
+                requested,
+            } => write!(
+                f,
+                "`{path}` is a {} ({} bytes), not a {requested}",
+                expected.type_name(),
+                expected.size_of()
+            ),
+            AccessError::OutOfBounds { offset, size, len } => write!(
+                f,
+                "{size} bytes at offset {offset} is out of bounds of {len} bytes"
+            ),
+            AccessError::Misaligned { offset, align } => {
+                write!(f, "offset {offset} isn't aligned to {align} bytes")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AccessError {}
+
+impl DynLayout {
+    /// Like `get_path` but says which segment of the path doesn't exist
+    pub fn try_get_path<'a>(&'a self, path: &[&str]) -> Result<&'a DynField, AccessError> {
+        let parents = path.split_last().ok_or(AccessError::EmptyPath)?.1;
+        let find = |layout: &'a DynLayout, i: usize| {
+            layout
+                .fields_hash
+                .get(path[i])
+                .ok_or_else(|| AccessError::PathNotFound {
+                    parent: path[..i].join("."),
+                    segment: path[i].to_string(),
+                })
+        };
+        let mut layout = self;
+        for i in 0..parents.len() {
+            // Every segment but the last has to be a struct or array
+            layout = find(layout, i)?
+                .ty
+                .layout()
+                .ok_or_else(|| AccessError::PathNotFound {
+                    parent: path[..=i].join("."),
+                    segment: path[i + 1].to_string(),
+                })?;
+        }
+        find(layout, parents.len())
+    }
+}
+
+fn check_type<T: IntoBaseType>(path: &[&str], field: &DynField) -> Result<usize, AccessError> {
+    if get_base_type::<T>() != field.ty {
+        return Err(AccessError::TypeMismatch {
+            path: path.join("."),
+            expected: field.ty.clone(),
+            requested: type_name::<T>(),
+        });
+    }
+    Ok(field.offset as usize)
+}
+
+fn check_range<T>(data: &[u8], offset: usize) -> Result<(), AccessError> {
+    let size = size_of::<T>();
+    if offset.checked_add(size).is_none_or(|end| end > data.len()) {
+        return Err(AccessError::OutOfBounds {
+            offset,
+            size,
+            len: data.len(),
+        });
+    }
+    let align = align_of::<T>();
+    if !(data.as_ptr() as usize + offset).is_multiple_of(align) {
+        return Err(AccessError::Misaligned { offset, align });
+    }
+    Ok(())
+}
+
+impl DynStruct {
+    /// Checked `get`
+    pub fn try_get<T: Pod + Zeroable + IntoBaseType>(
+        &self,
+        path: &[&str],
+    ) -> Result<&T, AccessError> {
+        let offset = check_type::<T>(path, self.layout.try_get_path(path)?)?;
+        self.try_get_raw(offset)
+    }
+
+    /// Checked `get_mut`
+    pub fn try_get_mut<T: Pod + Zeroable + IntoBaseType>(
+        &mut self,
+        path: &[&str],
+    ) -> Result<&mut T, AccessError> {
+        let offset = check_type::<T>(path, self.layout.try_get_path(path)?)?;
+        self.try_get_mut_raw(offset)
+    }
+
+    /// Checked `get_raw`
+    pub fn try_get_raw<T: Pod + Zeroable>(&self, offset: usize) -> Result<&T, AccessError> {
+        check_range::<T>(&self.data, offset)?;
+        Ok(self.get_raw(offset))
+    }
+
+    /// Checked `get_mut_raw`
+    pub fn try_get_mut_raw<T: Pod + Zeroable>(
+        &mut self,
+        offset: usize,
+    ) -> Result<&mut T, AccessError> {
+        check_range::<T>(&self.data, offset)?;
+        Ok(self.get_mut_raw(offset))
+    }
+}
+
+impl TrackedDynStruct {
+    /// Checked `get`
+    pub fn try_get<T: Pod + Zeroable + IntoBaseType>(
+        &self,
+        path: &[&str],
+    ) -> Result<&T, AccessError> {
+        self.dyn_struct.try_get(path)
+    }
+
+    /// Checked `get_mut`, only marks the field as changed if it is returned
+    pub fn try_get_mut<T: Pod + Zeroable + IntoBaseType>(
+        &mut self,
+        path: &[&str],
+    ) -> Result<&mut T, AccessError> {
+        let offset = check_type::<T>(path, self.dyn_struct.layout.try_get_path(path)?)?;
+        self.try_get_mut_raw(offset)
+    }
+
+    /// Checked `get_mut_raw`, only marks the field as changed if it is returned
+    pub fn try_get_mut_raw<T: Pod + Zeroable>(
+        &mut self,
+        offset: usize,
+    ) -> Result<&mut T, AccessError> {
+        check_range::<T>(&self.dyn_struct.data, offset)?;
+        Ok(self.get_mut_raw(offset))
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
+
+use bytemuck::{Pod, Zeroable};
+
+/// Alignment of `AlignedBytes`, the largest alignment of any `BaseType` (`u128`, `Mat4`, `Vec4`, ...)
+pub const MAX_ALIGN: usize = 16;
+
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Chunk([u8; MAX_ALIGN]);
+
+/// Byte buffer whose start is always aligned to `MAX_ALIGN`, so any field of a layout can be borrowed in place
+/// regardless of the allocator. Derefs to `[u8]`.
+#[derive(Clone, Default)]
+pub struct AlignedBytes {
+    chunks: Vec<Chunk>,
+    len: usize,
+}
+
+impl AlignedBytes {
+    pub fn zeroed(len: usize) -> Self {
+        AlignedBytes {
+            chunks: vec![Chunk::zeroed(); len.div_ceil(MAX_ALIGN)],
+            len,
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_slice(&self) -> &[u8] {
+        &bytemuck::cast_slice(&self.chunks)[..self.len]
+    }
+
+    #[inline(always)]
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut bytemuck::cast_slice_mut(&mut self.chunks)[..self.len]
+    }
+
+    /// Resizes to `len` bytes, new bytes are zero
+    pub fn resize(&mut self, len: usize) {
+        if len < self.len {
+            // Keep the bytes past `len` zeroed so growing again doesn't bring them back
+            let old_len = self.len;
+            self.len = len;
+            bytemuck::cast_slice_mut::<_, u8>(&mut self.chunks)[len..old_len].fill(0);
+        }
+        self.chunks.resize(len.div_ceil(MAX_ALIGN), Chunk::zeroed());
+        self.len = len;
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.as_slice().to_vec()
+    }
+}
+
+impl Deref for AlignedBytes {
+    type Target = [u8];
+
+    #[inline(always)]
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl DerefMut for AlignedBytes {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+}
+
+impl AsRef<[u8]> for AlignedBytes {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl AsMut<[u8]> for AlignedBytes {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+}
+
+impl<'a> IntoIterator for &'a AlignedBytes {
+    type Item = &'a u8;
+    type IntoIter = std::slice::Iter<'a, u8>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl From<&[u8]> for AlignedBytes {
+    fn from(bytes: &[u8]) -> Self {
+        let mut aligned = AlignedBytes::zeroed(bytes.len());
+        aligned.copy_from_slice(bytes);
+        aligned
+    }
+}
+
+impl From<Vec<u8>> for AlignedBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        AlignedBytes::from(bytes.as_slice())
+    }
+}
+
+impl From<AlignedBytes> for Vec<u8> {
+    fn from(bytes: AlignedBytes) -> Self {
+        bytes.into_vec()
+    }
+}
+
+impl fmt::Debug for AlignedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl PartialEq for AlignedBytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for AlignedBytes {}
+
+impl PartialEq<[u8]> for AlignedBytes {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl PartialEq<Vec<u8>> for AlignedBytes {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Hash for AlignedBytes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state)
+    }
+}
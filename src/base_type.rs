@@ -3,6 +3,7 @@ use std::sync::Arc;
 use crate::DynLayout;
 
 #[derive(Clone, Default, Debug, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BaseType {
     #[default]
     None,
@@ -40,8 +41,68 @@ pub enum BaseType {
     DAffine2,
     DAffine3,
     Struct(Arc<DynLayout>),
+    /// Fixed size array. Elements are fields named by their index ("0", "1", ...) so they can be accessed by path
+    /// like struct fields. See `DynLayout::new_array`
+    Array(Arc<DynLayout>),
+    /// Half precision float, `half::f16`
+    F16,
+    F16x2,
+    F16x3,
+    F16x4,
+    /// Normalized components packed into 32 bits, see `packed`. Decoded to floats by `DynValue`.
+    Unorm8x4,
+    Snorm8x4,
+    Unorm16x2,
+    Snorm16x2,
 }
 
+/// Every type except structs and arrays. The index is the type's tag in the binary file format (see `dyn_file`), so
+/// new types go at the end even when they are declared next to related variants of `BaseType`.
+pub static LEAF_TYPES: [BaseType; 42] = [
+    BaseType::None,
+    BaseType::U8,
+    BaseType::U16,
+    BaseType::U32,
+    BaseType::U64,
+    BaseType::U128,
+    BaseType::I8,
+    BaseType::I16,
+    BaseType::I32,
+    BaseType::I64,
+    BaseType::I128,
+    BaseType::F32,
+    BaseType::F64,
+    BaseType::UVec2,
+    BaseType::UVec3,
+    BaseType::UVec4,
+    BaseType::IVec2,
+    BaseType::IVec3,
+    BaseType::IVec4,
+    BaseType::Vec2,
+    BaseType::Vec3,
+    BaseType::Vec4,
+    BaseType::Mat2,
+    BaseType::Mat3,
+    BaseType::Mat4,
+    BaseType::Quat,
+    BaseType::DVec2,
+    BaseType::DVec3,
+    BaseType::DVec4,
+    BaseType::DMat2,
+    BaseType::DMat3,
+    BaseType::DMat4,
+    BaseType::DAffine2,
+    BaseType::DAffine3,
+    BaseType::F16,
+    BaseType::F16x2,
+    BaseType::F16x4,
+    BaseType::Unorm8x4,
+    BaseType::Snorm8x4,
+    BaseType::Unorm16x2,
+    BaseType::Snorm16x2,
+    BaseType::F16x3,
+];
+
 impl BaseType {
     pub fn rust_base_type(&self) -> bool {
         match &self {
@@ -80,12 +141,50 @@ impl BaseType {
             BaseType::DAffine2 => false,
             BaseType::DAffine3 => false,
             BaseType::Struct(_) => false,
+            BaseType::Array(_) => false,
+            BaseType::F16 => true,
+            BaseType::F16x2 => false,
+            BaseType::F16x3 => false,
+            BaseType::F16x4 => false,
+            BaseType::Unorm8x4 => false,
+            BaseType::Snorm8x4 => false,
+            BaseType::Unorm16x2 => false,
+            BaseType::Snorm16x2 => false,
+        }
+    }
+
+    /// Name used when displaying the type. Rust primitives are lowercase, structs and arrays use their layout name.
+    pub fn type_name(&self) -> String {
+        match self {
+            BaseType::Struct(layout) | BaseType::Array(layout) => layout.name.clone(),
+            ty if ty.rust_base_type() => format!("{ty:?}").to_lowercase(),
+            ty => format!("{ty:?}"),
+        }
+    }
+
+    /// Returns a copy of this type moved to `offset`. Struct and array layouts hold absolute offsets so they are
+    /// rebased, other types are just cloned.
+    pub fn rebased(&self, offset: u32) -> BaseType {
+        match self {
+            BaseType::Struct(layout) => BaseType::Struct(Arc::new(layout.rebased(offset))),
+            BaseType::Array(layout) => BaseType::Array(Arc::new(layout.rebased(offset))),
+            ty => ty.clone(),
+        }
+    }
+
+    /// Layout of a struct or array type.
+    #[inline(always)]
+    pub fn layout(&self) -> Option<&Arc<DynLayout>> {
+        match self {
+            BaseType::Struct(layout) | BaseType::Array(layout) => Some(layout),
+            _ => None,
         }
     }
 }
 
 pub trait BaseTypeInfo {
     const SIZE: usize;
+    const ALIGN: usize;
 }
 
 macro_rules! impl_base_type_info {
@@ -93,6 +192,7 @@ macro_rules! impl_base_type_info {
         $(
             impl BaseTypeInfo for $t {
                 const SIZE: usize = std::mem::size_of::<$t>();
+                const ALIGN: usize = std::mem::align_of::<$t>();
             }
 
             impl IntoBaseType for $t {
@@ -110,7 +210,7 @@ macro_rules! impl_base_type_info {
                         BaseType::$variant => Some(<$t as BaseTypeInfo>::SIZE),
                     )*
                     BaseType::None => Some(0),
-                    BaseType::Struct(_) => None,
+                    BaseType::Struct(_) | BaseType::Array(_) => None,
                 }
             }
             #[inline(always)]
@@ -120,7 +220,23 @@ macro_rules! impl_base_type_info {
                         BaseType::$variant => <$t as BaseTypeInfo>::SIZE,
                     )*
                     BaseType::None => 0,
-                    BaseType::Struct(s) => s.size as usize,
+                    BaseType::Struct(s) | BaseType::Array(s) => s.size as usize,
+                }
+            }
+            /// Alignment of the rust type. For structs and arrays this is the largest alignment of any field.
+            #[inline(always)]
+            pub fn align_of(&self) -> usize {
+                match self {
+                    $(
+                        BaseType::$variant => <$t as BaseTypeInfo>::ALIGN,
+                    )*
+                    BaseType::None => 1,
+                    BaseType::Struct(s) | BaseType::Array(s) => s
+                        .fields
+                        .iter()
+                        .map(|(_, field)| field.ty.align_of())
+                        .max()
+                        .unwrap_or(1),
                 }
             }
         }
@@ -169,5 +285,13 @@ impl_base_type_info!(
     glam::DMat3 => DMat3,
     glam::DMat4 => DMat4,
     glam::DAffine2 => DAffine2,
-    glam::DAffine3 => DAffine3
+    glam::DAffine3 => DAffine3,
+    half::f16 => F16,
+    crate::packed::F16x2 => F16x2,
+    crate::packed::F16x3 => F16x3,
+    crate::packed::F16x4 => F16x4,
+    crate::packed::Unorm8x4 => Unorm8x4,
+    crate::packed::Snorm8x4 => Snorm8x4,
+    crate::packed::Unorm16x2 => Unorm16x2,
+    crate::packed::Snorm16x2 => Snorm16x2
 );
@@ -2,7 +2,12 @@ use __macro_exports::RegisterForReflection;
 use bevy_reflect::*;
 use glam::*;
 
-use crate::{base_type::BaseType, dyn_struct::DynField, tracked_dyn_struct::TrackedDynStruct};
+use crate::{
+    base_type::BaseType,
+    dyn_struct::DynField,
+    packed::{F16x2, F16x3, F16x4, Snorm16x2, Snorm8x4, Unorm16x2, Unorm8x4},
+    tracked_dyn_struct::TrackedDynStruct,
+};
 
 impl PartialReflect for TrackedDynStruct {
     fn get_represented_type_info(&self) -> Option<&'static TypeInfo> {
@@ -82,27 +87,19 @@ impl Reflect for TrackedDynStruct {
 
 impl Struct for TrackedDynStruct {
     fn field(&self, name: &str) -> Option<&dyn PartialReflect> {
-        let Some(field) = self.dyn_struct.layout.get_path(&[name]) else {
-            return None;
-        };
+        let field = self.dyn_struct.layout.get_path(&[name])?;
         self.reflect_field(field)
     }
     fn field_mut(&mut self, name: &str) -> Option<&mut dyn PartialReflect> {
-        let Some(field) = self.dyn_struct.layout.get_path(&[name]) else {
-            return None;
-        };
+        let field = self.dyn_struct.layout.get_path(&[name])?;
         self.reflect_field_mut(&field.clone()) // TODO avoid clone
     }
     fn field_at(&self, index: usize) -> Option<&dyn PartialReflect> {
-        let Some((_, field)) = self.dyn_struct.layout.fields.get(index) else {
-            return None;
-        };
+        let (_, field) = self.dyn_struct.layout.fields.get(index)?;
         self.reflect_field(field)
     }
     fn field_at_mut(&mut self, index: usize) -> Option<&mut dyn PartialReflect> {
-        let Some((_, field)) = self.dyn_struct.layout.fields.get(index) else {
-            return None;
-        };
+        let (_, field) = self.dyn_struct.layout.fields.get(index)?;
         self.reflect_field_mut(&field.clone()) // TODO avoid clone
     }
     fn name_at(&self, index: usize) -> Option<&str> {
@@ -124,87 +121,109 @@ impl TrackedDynStruct {
     pub fn reflect_field(&self, field: &DynField) -> Option<&dyn PartialReflect> {
         let ofs = field.offset as usize;
         match &field.ty {
-            BaseType::None => return None,
-            BaseType::U8 => return Some(self.get_raw::<u8>(ofs)),
-            BaseType::U16 => return Some(self.get_raw::<u16>(ofs)),
-            BaseType::U32 => return Some(self.get_raw::<u32>(ofs)),
-            BaseType::U64 => return Some(self.get_raw::<u64>(ofs)),
-            BaseType::U128 => return Some(self.get_raw::<u128>(ofs)),
-            BaseType::I8 => return Some(self.get_raw::<i8>(ofs)),
-            BaseType::I16 => return Some(self.get_raw::<i16>(ofs)),
-            BaseType::I32 => return Some(self.get_raw::<i32>(ofs)),
-            BaseType::I64 => return Some(self.get_raw::<i64>(ofs)),
-            BaseType::I128 => return Some(self.get_raw::<i128>(ofs)),
-            BaseType::F32 => return Some(self.get_raw::<f32>(ofs)),
-            BaseType::F64 => return Some(self.get_raw::<f64>(ofs)),
-            BaseType::UVec2 => return Some(self.get_raw::<UVec2>(ofs)),
-            BaseType::UVec3 => return Some(self.get_raw::<UVec3>(ofs)),
-            BaseType::UVec4 => return Some(self.get_raw::<UVec4>(ofs)),
-            BaseType::IVec2 => return Some(self.get_raw::<IVec2>(ofs)),
-            BaseType::IVec3 => return Some(self.get_raw::<IVec3>(ofs)),
-            BaseType::IVec4 => return Some(self.get_raw::<IVec4>(ofs)),
-            BaseType::Vec2 => return Some(self.get_raw::<Vec2>(ofs)),
-            BaseType::Vec3 => return Some(self.get_raw::<Vec3>(ofs)),
-            BaseType::Vec4 => return Some(self.get_raw::<Vec4>(ofs)),
-            BaseType::Mat2 => return Some(self.get_raw::<Mat2>(ofs)),
-            BaseType::Mat3 => return Some(self.get_raw::<Mat3>(ofs)),
-            BaseType::Mat4 => return Some(self.get_raw::<Mat4>(ofs)),
-            BaseType::Quat => return Some(self.get_raw::<Quat>(ofs)),
-            BaseType::DVec2 => return Some(self.get_raw::<DVec2>(ofs)),
-            BaseType::DVec3 => return Some(self.get_raw::<DVec3>(ofs)),
-            BaseType::DVec4 => return Some(self.get_raw::<DVec4>(ofs)),
-            BaseType::DMat2 => return Some(self.get_raw::<DMat2>(ofs)),
-            BaseType::DMat3 => return Some(self.get_raw::<DMat3>(ofs)),
-            BaseType::DMat4 => return Some(self.get_raw::<DMat4>(ofs)),
-            BaseType::DAffine2 => return Some(self.get_raw::<DAffine2>(ofs)),
-            BaseType::DAffine3 => return Some(self.get_raw::<DAffine3>(ofs)),
+            BaseType::None => None,
+            BaseType::U8 => Some(self.get_raw::<u8>(ofs)),
+            BaseType::U16 => Some(self.get_raw::<u16>(ofs)),
+            BaseType::U32 => Some(self.get_raw::<u32>(ofs)),
+            BaseType::U64 => Some(self.get_raw::<u64>(ofs)),
+            BaseType::U128 => Some(self.get_raw::<u128>(ofs)),
+            BaseType::I8 => Some(self.get_raw::<i8>(ofs)),
+            BaseType::I16 => Some(self.get_raw::<i16>(ofs)),
+            BaseType::I32 => Some(self.get_raw::<i32>(ofs)),
+            BaseType::I64 => Some(self.get_raw::<i64>(ofs)),
+            BaseType::I128 => Some(self.get_raw::<i128>(ofs)),
+            BaseType::F32 => Some(self.get_raw::<f32>(ofs)),
+            BaseType::F64 => Some(self.get_raw::<f64>(ofs)),
+            BaseType::UVec2 => Some(self.get_raw::<UVec2>(ofs)),
+            BaseType::UVec3 => Some(self.get_raw::<UVec3>(ofs)),
+            BaseType::UVec4 => Some(self.get_raw::<UVec4>(ofs)),
+            BaseType::IVec2 => Some(self.get_raw::<IVec2>(ofs)),
+            BaseType::IVec3 => Some(self.get_raw::<IVec3>(ofs)),
+            BaseType::IVec4 => Some(self.get_raw::<IVec4>(ofs)),
+            BaseType::Vec2 => Some(self.get_raw::<Vec2>(ofs)),
+            BaseType::Vec3 => Some(self.get_raw::<Vec3>(ofs)),
+            BaseType::Vec4 => Some(self.get_raw::<Vec4>(ofs)),
+            BaseType::Mat2 => Some(self.get_raw::<Mat2>(ofs)),
+            BaseType::Mat3 => Some(self.get_raw::<Mat3>(ofs)),
+            BaseType::Mat4 => Some(self.get_raw::<Mat4>(ofs)),
+            BaseType::Quat => Some(self.get_raw::<Quat>(ofs)),
+            BaseType::DVec2 => Some(self.get_raw::<DVec2>(ofs)),
+            BaseType::DVec3 => Some(self.get_raw::<DVec3>(ofs)),
+            BaseType::DVec4 => Some(self.get_raw::<DVec4>(ofs)),
+            BaseType::DMat2 => Some(self.get_raw::<DMat2>(ofs)),
+            BaseType::DMat3 => Some(self.get_raw::<DMat3>(ofs)),
+            BaseType::DMat4 => Some(self.get_raw::<DMat4>(ofs)),
+            BaseType::DAffine2 => Some(self.get_raw::<DAffine2>(ofs)),
+            BaseType::DAffine3 => Some(self.get_raw::<DAffine3>(ofs)),
+            BaseType::F16x2 => Some(self.get_raw::<F16x2>(ofs)),
+            BaseType::F16x3 => Some(self.get_raw::<F16x3>(ofs)),
+            BaseType::F16x4 => Some(self.get_raw::<F16x4>(ofs)),
+            BaseType::Unorm8x4 => Some(self.get_raw::<Unorm8x4>(ofs)),
+            BaseType::Snorm8x4 => Some(self.get_raw::<Snorm8x4>(ofs)),
+            BaseType::Unorm16x2 => Some(self.get_raw::<Unorm16x2>(ofs)),
+            BaseType::Snorm16x2 => Some(self.get_raw::<Snorm16x2>(ofs)),
+            // `half::f16` doesn't implement Reflect and can't be given an impl here, use `get_unpacked` instead
+            BaseType::F16 => None,
+            // Arrays would need the same kind of reference as structs
+            BaseType::Array(_) => None,
             // TODO Need a DynFieldRef that can hold this field and a slice of bytes
             // How do we return a reference to the new DynFieldRef though?
             BaseType::Struct(_arc) => todo!(),
-        };
+        }
     }
 
     fn reflect_field_mut(&mut self, field: &DynField) -> Option<&mut dyn PartialReflect> {
         let ofs = field.offset as usize;
         match &field.ty {
-            BaseType::None => return None,
-            BaseType::U8 => return Some(self.get_mut_raw::<u8>(ofs)),
-            BaseType::U16 => return Some(self.get_mut_raw::<u16>(ofs)),
-            BaseType::U32 => return Some(self.get_mut_raw::<u32>(ofs)),
-            BaseType::U64 => return Some(self.get_mut_raw::<u64>(ofs)),
-            BaseType::U128 => return Some(self.get_mut_raw::<u128>(ofs)),
-            BaseType::I8 => return Some(self.get_mut_raw::<i8>(ofs)),
-            BaseType::I16 => return Some(self.get_mut_raw::<i16>(ofs)),
-            BaseType::I32 => return Some(self.get_mut_raw::<i32>(ofs)),
-            BaseType::I64 => return Some(self.get_mut_raw::<i64>(ofs)),
-            BaseType::I128 => return Some(self.get_mut_raw::<i128>(ofs)),
-            BaseType::F32 => return Some(self.get_mut_raw::<f32>(ofs)),
-            BaseType::F64 => return Some(self.get_mut_raw::<f64>(ofs)),
-            BaseType::UVec2 => return Some(self.get_mut_raw::<UVec2>(ofs)),
-            BaseType::UVec3 => return Some(self.get_mut_raw::<UVec3>(ofs)),
-            BaseType::UVec4 => return Some(self.get_mut_raw::<UVec4>(ofs)),
-            BaseType::IVec2 => return Some(self.get_mut_raw::<IVec2>(ofs)),
-            BaseType::IVec3 => return Some(self.get_mut_raw::<IVec3>(ofs)),
-            BaseType::IVec4 => return Some(self.get_mut_raw::<IVec4>(ofs)),
-            BaseType::Vec2 => return Some(self.get_mut_raw::<Vec2>(ofs)),
-            BaseType::Vec3 => return Some(self.get_mut_raw::<Vec3>(ofs)),
-            BaseType::Vec4 => return Some(self.get_mut_raw::<Vec4>(ofs)),
-            BaseType::Mat2 => return Some(self.get_mut_raw::<Mat2>(ofs)),
-            BaseType::Mat3 => return Some(self.get_mut_raw::<Mat3>(ofs)),
-            BaseType::Mat4 => return Some(self.get_mut_raw::<Mat4>(ofs)),
-            BaseType::Quat => return Some(self.get_mut_raw::<Quat>(ofs)),
-            BaseType::DVec2 => return Some(self.get_mut_raw::<DVec2>(ofs)),
-            BaseType::DVec3 => return Some(self.get_mut_raw::<DVec3>(ofs)),
-            BaseType::DVec4 => return Some(self.get_mut_raw::<DVec4>(ofs)),
-            BaseType::DMat2 => return Some(self.get_mut_raw::<DMat2>(ofs)),
-            BaseType::DMat3 => return Some(self.get_mut_raw::<DMat3>(ofs)),
-            BaseType::DMat4 => return Some(self.get_mut_raw::<DMat4>(ofs)),
-            BaseType::DAffine2 => return Some(self.get_mut_raw::<DAffine2>(ofs)),
-            BaseType::DAffine3 => return Some(self.get_mut_raw::<DAffine3>(ofs)),
+            BaseType::None => None,
+            BaseType::U8 => Some(self.get_mut_raw::<u8>(ofs)),
+            BaseType::U16 => Some(self.get_mut_raw::<u16>(ofs)),
+            BaseType::U32 => Some(self.get_mut_raw::<u32>(ofs)),
+            BaseType::U64 => Some(self.get_mut_raw::<u64>(ofs)),
+            BaseType::U128 => Some(self.get_mut_raw::<u128>(ofs)),
+            BaseType::I8 => Some(self.get_mut_raw::<i8>(ofs)),
+            BaseType::I16 => Some(self.get_mut_raw::<i16>(ofs)),
+            BaseType::I32 => Some(self.get_mut_raw::<i32>(ofs)),
+            BaseType::I64 => Some(self.get_mut_raw::<i64>(ofs)),
+            BaseType::I128 => Some(self.get_mut_raw::<i128>(ofs)),
+            BaseType::F32 => Some(self.get_mut_raw::<f32>(ofs)),
+            BaseType::F64 => Some(self.get_mut_raw::<f64>(ofs)),
+            BaseType::UVec2 => Some(self.get_mut_raw::<UVec2>(ofs)),
+            BaseType::UVec3 => Some(self.get_mut_raw::<UVec3>(ofs)),
+            BaseType::UVec4 => Some(self.get_mut_raw::<UVec4>(ofs)),
+            BaseType::IVec2 => Some(self.get_mut_raw::<IVec2>(ofs)),
+            BaseType::IVec3 => Some(self.get_mut_raw::<IVec3>(ofs)),
+            BaseType::IVec4 => Some(self.get_mut_raw::<IVec4>(ofs)),
+            BaseType::Vec2 => Some(self.get_mut_raw::<Vec2>(ofs)),
+            BaseType::Vec3 => Some(self.get_mut_raw::<Vec3>(ofs)),
+            BaseType::Vec4 => Some(self.get_mut_raw::<Vec4>(ofs)),
+            BaseType::Mat2 => Some(self.get_mut_raw::<Mat2>(ofs)),
+            BaseType::Mat3 => Some(self.get_mut_raw::<Mat3>(ofs)),
+            BaseType::Mat4 => Some(self.get_mut_raw::<Mat4>(ofs)),
+            BaseType::Quat => Some(self.get_mut_raw::<Quat>(ofs)),
+            BaseType::DVec2 => Some(self.get_mut_raw::<DVec2>(ofs)),
+            BaseType::DVec3 => Some(self.get_mut_raw::<DVec3>(ofs)),
+            BaseType::DVec4 => Some(self.get_mut_raw::<DVec4>(ofs)),
+            BaseType::DMat2 => Some(self.get_mut_raw::<DMat2>(ofs)),
+            BaseType::DMat3 => Some(self.get_mut_raw::<DMat3>(ofs)),
+            BaseType::DMat4 => Some(self.get_mut_raw::<DMat4>(ofs)),
+            BaseType::DAffine2 => Some(self.get_mut_raw::<DAffine2>(ofs)),
+            BaseType::DAffine3 => Some(self.get_mut_raw::<DAffine3>(ofs)),
+            BaseType::F16x2 => Some(self.get_mut_raw::<F16x2>(ofs)),
+            BaseType::F16x3 => Some(self.get_mut_raw::<F16x3>(ofs)),
+            BaseType::F16x4 => Some(self.get_mut_raw::<F16x4>(ofs)),
+            BaseType::Unorm8x4 => Some(self.get_mut_raw::<Unorm8x4>(ofs)),
+            BaseType::Snorm8x4 => Some(self.get_mut_raw::<Snorm8x4>(ofs)),
+            BaseType::Unorm16x2 => Some(self.get_mut_raw::<Unorm16x2>(ofs)),
+            BaseType::Snorm16x2 => Some(self.get_mut_raw::<Snorm16x2>(ofs)),
+            // `half::f16` doesn't implement Reflect and can't be given an impl here, use `get_unpacked` instead
+            BaseType::F16 => None,
+            // Arrays would need the same kind of reference as structs
+            BaseType::Array(_) => None,
             // TODO Need a DynFieldRefMut that can hold this field and a slice of bytes
             // How do we return a reference to the new DynFieldRefMut though?
             BaseType::Struct(_arc) => todo!(),
-        };
+        }
     }
 }
 
@@ -260,6 +279,13 @@ where
         <DMat4 as RegisterForReflection>::__register(registry);
         <DAffine2 as RegisterForReflection>::__register(registry);
         <DAffine3 as RegisterForReflection>::__register(registry);
+        registry.register::<F16x2>();
+        registry.register::<F16x3>();
+        registry.register::<F16x4>();
+        registry.register::<Unorm8x4>();
+        registry.register::<Snorm8x4>();
+        registry.register::<Unorm16x2>();
+        registry.register::<Snorm16x2>();
     }
 }
 
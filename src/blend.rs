@@ -0,0 +1,202 @@
+use bytemuck::{bytes_of, pod_read_unaligned};
+use fxhash::FxHashMap;
+use glam::{DMat4, DQuat, Mat4, Quat};
+
+use crate::{
+    base_type::BaseType,
+    dyn_struct::DynStruct,
+    dyn_value::{DynValue, Scalar},
+    tracked_dyn_struct::TrackedDynStruct,
+};
+
+/// How integer fields are blended
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IntegerPolicy {
+    /// `a` while `t < 0.5`, then `b`
+    #[default]
+    Step,
+    /// Always `a`
+    KeepA,
+    /// Linear interpolation rounded to the nearest integer
+    Round,
+}
+
+/// How `Mat4` and `DMat4` fields are blended, other matrices are always blended component-wise
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MatrixPolicy {
+    #[default]
+    ComponentWise,
+    /// Decompose into scale, rotation and translation, lerp scale and translation and slerp the rotation. Keeps
+    /// rotations rigid, but shear is lost.
+    Decompose,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlendOptions {
+    pub integers: IntegerPolicy,
+    /// Per field overrides of `integers` by path
+    pub field_integers: FxHashMap<String, IntegerPolicy>,
+    pub matrices: MatrixPolicy,
+}
+
+impl BlendOptions {
+    pub fn with_field_integers(mut self, path: &str, policy: IntegerPolicy) -> Self {
+        self.field_integers.insert(path.to_string(), policy);
+        self
+    }
+}
+
+/// Blends one leaf field of type `ty` from `a` towards `b`, writing the result to `out`
+fn blend_field(
+    ty: &BaseType,
+    a: &[u8],
+    b: &[u8],
+    t: f32,
+    integers: IntegerPolicy,
+    matrices: MatrixPolicy,
+    out: &mut [u8],
+) {
+    match (ty, matrices) {
+        (BaseType::Quat, _) => {
+            let a: Quat = pod_read_unaligned(a);
+            let b: Quat = pod_read_unaligned(b);
+            out.copy_from_slice(bytes_of(&a.slerp(b, t)));
+        }
+        (BaseType::Mat4, MatrixPolicy::Decompose) => {
+            let (a_scale, a_rotation, a_translation) =
+                pod_read_unaligned::<Mat4>(a).to_scale_rotation_translation();
+            let (b_scale, b_rotation, b_translation) =
+                pod_read_unaligned::<Mat4>(b).to_scale_rotation_translation();
+            let blended = Mat4::from_scale_rotation_translation(
+                a_scale.lerp(b_scale, t),
+                a_rotation.slerp(b_rotation, t),
+                a_translation.lerp(b_translation, t),
+            );
+            out.copy_from_slice(bytes_of(&blended));
+        }
+        (BaseType::DMat4, MatrixPolicy::Decompose) => {
+            let t = t as f64;
+            let (a_scale, a_rotation, a_translation) =
+                pod_read_unaligned::<DMat4>(a).to_scale_rotation_translation();
+            let (b_scale, b_rotation, b_translation) =
+                pod_read_unaligned::<DMat4>(b).to_scale_rotation_translation();
+            let rotation: DQuat = a_rotation.slerp(b_rotation, t);
+            let blended = DMat4::from_scale_rotation_translation(
+                a_scale.lerp(b_scale, t),
+                rotation,
+                a_translation.lerp(b_translation, t),
+            );
+            out.copy_from_slice(bytes_of(&blended));
+        }
+        _ => {
+            let (Some(a), Some(b)) = (DynValue::read(ty, a), DynValue::read(ty, b)) else {
+                return;
+            };
+            let t = t as f64;
+            let components = a
+                .components
+                .iter()
+                .zip(&b.components)
+                .map(|(a, b)| match (a, b) {
+                    (Scalar::Float(a), Scalar::Float(b)) => Scalar::Float(a + (b - a) * t),
+                    _ => match integers {
+                        IntegerPolicy::KeepA => *a,
+                        IntegerPolicy::Step if t < 0.5 => *a,
+                        IntegerPolicy::Step => *b,
+                        IntegerPolicy::Round => {
+                            let value = (a.as_f64() + (b.as_f64() - a.as_f64()) * t).round();
+                            match a {
+                                Scalar::Unsigned(_) => Scalar::Unsigned(value as u128),
+                                _ => Scalar::Signed(value as i128),
+                            }
+                        }
+                    },
+                })
+                .collect();
+            DynValue {
+                ty: ty.clone(),
+                components,
+            }
+            .write(out);
+        }
+    }
+}
+
+impl DynStruct {
+    /// Interpolates every leaf field from `a` (t = 0) to `b` (t = 1), see `lerp_with`.
+    pub fn lerp(a: &DynStruct, b: &DynStruct, t: f32) -> DynStruct {
+        Self::lerp_with(a, b, t, &BlendOptions::default())
+    }
+
+    /// Interpolates every leaf field from `a` to `b`. Floats, vectors and matrices are interpolated linearly, `Quat`
+    /// with slerp and integers according to `options`. Padding is copied from `a`.
+    /// Panics if the layouts differ.
+    pub fn lerp_with(a: &DynStruct, b: &DynStruct, t: f32, options: &BlendOptions) -> DynStruct {
+        let mut out = a.clone();
+        out.blend_into(b, t, options);
+        out
+    }
+
+    /// Blends `self` towards `target` in place, like `lerp_with(self, target, t, options)`.
+    pub fn blend_into(&mut self, target: &DynStruct, t: f32, options: &BlendOptions) {
+        blend_leaves(self, target, t, options, |_, _| ());
+    }
+}
+
+impl TrackedDynStruct {
+    /// `DynStruct::blend_into` that only marks the fields whose bytes changed. Returns whether anything changed.
+    pub fn blend_into(&mut self, target: &DynStruct, t: f32, options: &BlendOptions) -> bool {
+        let mut changed = Vec::new();
+        blend_leaves(&mut self.dyn_struct, target, t, options, |offset, old| {
+            changed.push((offset, old.to_vec()))
+        });
+        let mut any = false;
+        for (offset, old) in changed {
+            any |= self.mark_differing(offset, &old);
+        }
+        any
+    }
+}
+
+/// Blends each leaf of `dyn_struct` towards `target`, calling `on_write` with the offset and old bytes of every
+/// field that was written
+fn blend_leaves(
+    dyn_struct: &mut DynStruct,
+    target: &DynStruct,
+    t: f32,
+    options: &BlendOptions,
+    mut on_write: impl FnMut(usize, &[u8]),
+) {
+    assert!(
+        dyn_struct.layout == target.layout,
+        "Can't blend structs with different layouts ({} and {})",
+        dyn_struct.layout.name,
+        target.layout.name
+    );
+    let layout = dyn_struct.layout.clone();
+    let mut out = Vec::new();
+    for (path, field) in layout.leaves() {
+        let range = field.offset as usize..field.offset as usize + field.ty.size_of();
+        let integers = options
+            .field_integers
+            .get(path)
+            .copied()
+            .unwrap_or(options.integers);
+        out.clear();
+        out.resize(range.len(), 0);
+        let a = &dyn_struct.data[range.clone()];
+        blend_field(
+            &field.ty,
+            a,
+            &target.data[range.clone()],
+            t,
+            integers,
+            options.matrices,
+            &mut out,
+        );
+        if a != out.as_slice() {
+            on_write(range.start, a);
+            dyn_struct.data[range].copy_from_slice(&out);
+        }
+    }
+}
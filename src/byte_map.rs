@@ -0,0 +1,132 @@
+use std::fmt::{self, Display};
+
+use crate::dyn_layout::DynLayout;
+
+const KEYS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+const PADDING_KEY: char = '.';
+const TRAILING_PADDING_KEY: char = '_';
+// ANSI background colors cycled through for fields
+const COLORS: &[u8] = &[41, 42, 43, 44, 45, 46, 101, 102, 103, 104, 105, 106];
+const RESET: &str = "\x1b[0m";
+
+/// Map of which field owns each byte of a struct, including padding holes and trailing padding.
+/// Fields that straddle a 16 byte vector boundary are marked with `!` in the key (and underlined with ANSI).
+///
+/// println!("{}", layout.byte_map());
+/// println!("{}", layout.byte_map().ansi(true).bytes_per_row(32));
+#[derive(Clone, Debug)]
+pub struct ByteMap<'a> {
+    layout: &'a DynLayout,
+    ansi: bool,
+    bytes_per_row: usize,
+}
+
+impl DynLayout {
+    /// Renders a byte map of the struct with 16 bytes per row. Unlike `format_with_offsets` this shows the gaps
+    /// between fields.
+    pub fn byte_map(&self) -> ByteMap<'_> {
+        ByteMap {
+            layout: self,
+            ansi: false,
+            bytes_per_row: 16,
+        }
+    }
+}
+
+impl ByteMap<'_> {
+    /// Use ANSI escape codes to color each field.
+    pub fn ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    pub fn bytes_per_row(mut self, bytes_per_row: usize) -> Self {
+        self.bytes_per_row = bytes_per_row.max(1);
+        self
+    }
+}
+
+enum Owner {
+    Field(usize),
+    Padding,
+    TrailingPadding,
+}
+
+impl Display for ByteMap<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let layout = self.layout;
+        let start = layout.fields.first().map(|(_, f)| f.offset).unwrap_or(0) as usize;
+        let leaves = layout.leaves();
+
+        let mut owners = (0..layout.size).map(|_| Owner::Padding).collect::<Vec<_>>();
+        let mut straddles = vec![false; leaves.len()];
+        for (i, (_, field)) in leaves.iter().enumerate() {
+            let offset = field.offset as usize - start;
+            let size = field.ty.size_of();
+            for owner in owners.iter_mut().skip(offset).take(size) {
+                *owner = Owner::Field(i);
+            }
+            straddles[i] = size > 0 && size <= 16 && offset / 16 != (offset + size - 1) / 16;
+        }
+        if let Some(trailing) = layout.padding().last() {
+            if trailing.end - start == layout.size {
+                for owner in &mut owners[trailing.start - start..] {
+                    if let Owner::Padding = owner {
+                        *owner = Owner::TrailingPadding;
+                    }
+                }
+            }
+        }
+
+        let key = |i: usize| KEYS[i % KEYS.len()] as char;
+        let color = |i: usize| COLORS[i % COLORS.len()];
+
+        writeln!(f, "{} ({} bytes)", layout.name, layout.size)?;
+        write!(f, "offset")?;
+        for column in 0..self.bytes_per_row {
+            write!(f, "{column:>3}")?;
+        }
+        writeln!(f)?;
+        for (row, chunk) in owners.chunks(self.bytes_per_row).enumerate() {
+            write!(f, "{:>6}", row * self.bytes_per_row)?;
+            for owner in chunk {
+                match owner {
+                    Owner::Field(i) if self.ansi => {
+                        let underline = if straddles[*i] { "\x1b[4m" } else { "" };
+                        write!(f, "\x1b[{}m{underline}{:>3}{RESET}", color(*i), key(*i))?
+                    }
+                    Owner::Field(i) => write!(f, "{:>3}", key(*i))?,
+                    Owner::Padding if self.ansi => write!(f, "\x1b[2m{PADDING_KEY:>3}{RESET}")?,
+                    Owner::Padding => write!(f, "{PADDING_KEY:>3}")?,
+                    Owner::TrailingPadding if self.ansi => {
+                        write!(f, "\x1b[2m{TRAILING_PADDING_KEY:>3}{RESET}")?
+                    }
+                    Owner::TrailingPadding => write!(f, "{TRAILING_PADDING_KEY:>3}")?,
+                }
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f)?;
+        for (i, (path, field)) in leaves.iter().enumerate() {
+            let offset = field.offset as usize - start;
+            let end = offset + field.ty.size_of();
+            let ty_name = field.ty.type_name();
+            let straddle = if straddles[i] {
+                " ! straddles 16 byte boundary"
+            } else {
+                ""
+            };
+            if self.ansi {
+                write!(f, "\x1b[{}m{:>3}{RESET}", color(i), key(i))?;
+            } else {
+                write!(f, "{:>3}", key(i))?;
+            }
+            writeln!(f, "  {path}: {ty_name} ({offset}..{end}){straddle}")?;
+        }
+        let padding = layout.padding().iter().map(|r| r.len()).sum::<usize>();
+        writeln!(f, "{PADDING_KEY:>3}  padding")?;
+        writeln!(f, "{TRAILING_PADDING_KEY:>3}  trailing padding")?;
+        write!(f, "     {padding} bytes of padding")
+    }
+}
@@ -0,0 +1,195 @@
+use std::fmt::{self, Display};
+
+use fxhash::FxHashMap;
+
+use crate::tracked_dyn_struct::TrackedDynStruct;
+
+/// Counters of how much data `TrackedDynStruct` marks as changed. Enable with `TrackedDynStruct::enable_stats`.
+/// `reset_change_detection` (also called by `RetainedUploadBatch::add`) defines a frame and records it once, the
+/// consumer calls (`retrieve_changes_for`, `ack`, ...) never record frames. With consumers call
+/// `reset_change_detection` once per frame to keep counting.
+/// Stats of several structs can be combined with `merge`, see `RetainedUploadBatch::change_stats`.
+///
+/// println!("{}", instance.stats().unwrap().report(5));
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChangeStats {
+    /// Number of frames recorded, summed over every struct when merged
+    pub frames: usize,
+    /// Bytes passed to `mark_changed` (or marked by `get_mut`, `set`, ...)
+    pub bytes_marked: usize,
+    /// Update stride blocks that were marked when the frame was recorded
+    pub blocks_marked: usize,
+    /// Contiguous runs of marked blocks, the number of copies `retrieve_changes_coalesced` makes with no gap
+    pub runs: usize,
+    /// Bytes of the marked blocks, what gets uploaded
+    pub bytes_uploaded: usize,
+    /// Bytes of the marked blocks that actually differ from the previous frame
+    pub bytes_changed: usize,
+    /// Number of frames each leaf field (see `DynLayout::leaves`) overlapped a marked block, by path
+    pub field_counts: FxHashMap<String, usize>,
+}
+
+impl ChangeStats {
+    /// Bytes uploaded per byte that actually changed. 1.0 is perfect, larger means `update_stride` is too coarse or
+    /// fields are marked without being changed.
+    pub fn write_amplification(&self) -> f32 {
+        if self.bytes_changed == 0 {
+            if self.bytes_uploaded == 0 {
+                1.0
+            } else {
+                f32::INFINITY
+            }
+        } else {
+            self.bytes_uploaded as f32 / self.bytes_changed as f32
+        }
+    }
+
+    /// The `n` fields marked in the most frames, most frequent first
+    pub fn top_fields(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut fields = self
+            .field_counts
+            .iter()
+            .map(|(path, count)| (path.as_str(), *count))
+            .collect::<Vec<_>>();
+        fields.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        fields.truncate(n);
+        fields
+    }
+
+    /// Adds the counters of `other`
+    pub fn merge(&mut self, other: &ChangeStats) {
+        self.frames += other.frames;
+        self.bytes_marked += other.bytes_marked;
+        self.blocks_marked += other.blocks_marked;
+        self.runs += other.runs;
+        self.bytes_uploaded += other.bytes_uploaded;
+        self.bytes_changed += other.bytes_changed;
+        for (path, count) in &other.field_counts {
+            *self.field_counts.entry(path.clone()).or_default() += count;
+        }
+    }
+
+    /// Report listing the `top_n` most frequently marked fields. `Display` for `ChangeStats` lists 10.
+    pub fn report(&self, top_n: usize) -> ChangeReport<'_> {
+        ChangeReport { stats: self, top_n }
+    }
+}
+
+/// See `ChangeStats::report`
+#[derive(Clone, Debug)]
+pub struct ChangeReport<'a> {
+    stats: &'a ChangeStats,
+    top_n: usize,
+}
+
+impl Display for ChangeReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stats = self.stats;
+        writeln!(f, "Change stats over {} frames", stats.frames)?;
+        writeln!(f, "  bytes marked:        {}", stats.bytes_marked)?;
+        writeln!(f, "  blocks marked:       {}", stats.blocks_marked)?;
+        writeln!(f, "  runs:                {}", stats.runs)?;
+        writeln!(f, "  bytes uploaded:      {}", stats.bytes_uploaded)?;
+        writeln!(f, "  bytes changed:       {}", stats.bytes_changed)?;
+        write!(
+            f,
+            "  write amplification: {:.2}x",
+            stats.write_amplification()
+        )?;
+        let top_fields = stats.top_fields(self.top_n);
+        if !top_fields.is_empty() {
+            write!(f, "\nMost marked fields:")?;
+            for (path, count) in top_fields {
+                write!(f, "\n  {count:>6}  {path}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for ChangeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.report(10).fmt(f)
+    }
+}
+
+/// Stats state kept by a `TrackedDynStruct` with stats enabled
+#[derive(Clone, Debug)]
+pub(crate) struct StatsState {
+    total: ChangeStats,
+    frame: ChangeStats,
+    last_frame: ChangeStats,
+    /// The data as of the last recorded frame, to count the bytes that actually changed
+    previous: Vec<u8>,
+}
+
+impl TrackedDynStruct {
+    /// Start collecting `ChangeStats`. Costs a copy of the data and some bookkeeping on every change, so only enable
+    /// it when profiling.
+    pub fn enable_stats(&mut self) {
+        self.stats = Some(Box::new(StatsState {
+            total: ChangeStats::default(),
+            frame: ChangeStats::default(),
+            last_frame: ChangeStats::default(),
+            previous: self.dyn_struct.data.to_vec(),
+        }));
+    }
+
+    pub fn disable_stats(&mut self) {
+        self.stats = None;
+    }
+
+    /// Stats of every recorded frame, None if stats aren't enabled
+    pub fn stats(&self) -> Option<&ChangeStats> {
+        self.stats.as_ref().map(|state| &state.total)
+    }
+
+    /// Stats of the most recently recorded frame
+    pub fn last_frame_stats(&self) -> Option<&ChangeStats> {
+        self.stats.as_ref().map(|state| &state.last_frame)
+    }
+
+    pub fn reset_stats(&mut self) {
+        if let Some(state) = &mut self.stats {
+            state.total = ChangeStats::default();
+            state.last_frame = ChangeStats::default();
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn count_marked_bytes(&mut self, bytes: usize) {
+        if let Some(state) = &mut self.stats {
+            state.frame.bytes_marked += bytes;
+        }
+    }
+
+    /// Records the blocks currently marked as one frame. Only called by `reset_change_detection`, before
+    /// `update_bitmask` is reset.
+    pub(crate) fn record_stats_frame(&mut self) {
+        let Some(mut state) = self.stats.take() else {
+            return;
+        };
+        let stride = self.update_stride();
+        let data = &self.dyn_struct.data;
+        let mut frame = std::mem::take(&mut state.frame);
+        frame.frames = 1;
+        for range in self.update_bitmask.ranges() {
+            let bytes = range.start * stride..(range.end * stride).min(data.len());
+            frame.blocks_marked += range.len();
+            frame.runs += 1;
+            frame.bytes_uploaded += bytes.len();
+            frame.bytes_changed += data[bytes.clone()]
+                .iter()
+                .zip(&state.previous[bytes.clone()])
+                .filter(|(new, old)| new != old)
+                .count();
+            state.previous[bytes.clone()].copy_from_slice(&data[bytes]);
+        }
+        for (path, _) in self.changed_fields() {
+            frame.field_counts.insert(path.to_string(), 1);
+        }
+        state.total.merge(&frame);
+        state.last_frame = frame;
+        self.stats = Some(state);
+    }
+}
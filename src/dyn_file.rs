@@ -0,0 +1,401 @@
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use crate::{
+    base_type::{BaseType, LEAF_TYPES},
+    dyn_layout::DynLayout,
+    dyn_patch::write_varint,
+    dyn_struct::{DynField, DynStruct},
+    dyn_view::DynView,
+};
+use xxhash_rust::xxh64::{xxh64, Xxh64};
+
+// File layout, header fields are little endian:
+//   0  magic            [u8; 8]
+//   8  version          u16
+//  10  endianness       u8, of the data blocks (1 little, 2 big)
+//  11  reserved         u8
+//  12  count            u32, number of structs
+//  16  layout hash      u64, xxh64 (seed 0) of the layout bytes, `DynLayout::layout_hash`
+//  24  layout length    u32
+//  28  data offset      u32, aligned to 16 from the start of the file
+//  32  data length      u64
+//  40  checksum         u64, xxh64 (seed 0) of the layout bytes followed by the data bytes
+//  48  layout           see `write_layout`
+//      data             `count` structs back to back
+// Both hashes are over the raw bytes as stored, so they don't depend on the compiler or platform.
+pub const FILE_MAGIC: [u8; 8] = *b"DYNPOD\0\0";
+/// Version 1 is the layout above, with both hashes xxh64 with seed 0 over the stored bytes
+pub const FILE_VERSION: u16 = 1;
+const HEADER_SIZE: usize = 48;
+const DATA_ALIGN: usize = 16;
+const STRUCT_TAG: u8 = 0xfe;
+const ARRAY_TAG: u8 = 0xff;
+/// Deeper layouts are rejected so a corrupt file can't overflow the stack
+const MAX_DEPTH: usize = 64;
+
+#[cfg(target_endian = "little")]
+const NATIVE_ENDIANNESS: u8 = 1;
+#[cfg(target_endian = "big")]
+const NATIVE_ENDIANNESS: u8 = 2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileError {
+    /// Not a dyn file
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// The data was written on a machine with different endianness
+    EndiannessMismatch,
+    /// The file is shorter than the header says it should be
+    Truncated {
+        expected: usize,
+        len: usize,
+    },
+    ChecksumMismatch,
+    /// The layout couldn't be decoded or doesn't fit its data
+    CorruptLayout,
+    /// The decoded layout doesn't match the hash it was saved with
+    LayoutHashMismatch,
+    /// The file's layout isn't the expected one, see `DynFile::expect_layout`
+    LayoutMismatch {
+        expected: String,
+        found: String,
+    },
+    /// The file doesn't hold the expected number of structs
+    CountMismatch {
+        expected: usize,
+        count: usize,
+    },
+    /// The data or layout doesn't fit the file's 32 bit header fields
+    TooLarge,
+}
+
+impl Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::BadMagic => write!(f, "not a dyn_pod_struct file"),
+            FileError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported file version {version}, expected {FILE_VERSION}"
+            ),
+            FileError::EndiannessMismatch => {
+                write!(f, "file was written with a different endianness")
+            }
+            FileError::Truncated { expected, len } => write!(
+                f,
+                "file is truncated, expected {expected} bytes but got {len}"
+            ),
+            FileError::ChecksumMismatch => write!(f, "checksum mismatch, the file is corrupted"),
+            FileError::CorruptLayout => write!(f, "the layout in the file is corrupted"),
+            FileError::LayoutHashMismatch => write!(f, "layout doesn't match its hash"),
+            FileError::LayoutMismatch { expected, found } => {
+                write!(f, "expected layout {expected}, file has {found}")
+            }
+            FileError::CountMismatch { expected, count } => {
+                write!(f, "expected {expected} structs, file has {count}")
+            }
+            FileError::TooLarge => write!(f, "too much data to fit in a file"),
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
+
+/// Structs loaded from a file without copying. The data borrows from the file bytes, so if those are aligned to 16
+/// (e.g. read into `AlignedBytes`) `DynView::get` can borrow every field, otherwise use `DynView::read_unaligned`.
+///
+/// let bytes = std::fs::read("instances.dyn")?;
+/// let file = DynFile::parse(&bytes)?;
+/// let position = file.get(3).read_unaligned::<Vec3>(&["position"]);
+#[derive(Clone, Debug)]
+pub struct DynFile<'a> {
+    pub layout: Arc<DynLayout>,
+    /// `len()` structs back to back
+    pub data: &'a [u8],
+}
+
+impl<'a> DynFile<'a> {
+    /// Writes `data`, a whole number of structs of `layout`, as a file. Fails with `TooLarge` if the struct count,
+    /// layout or data offset don't fit in 32 bits.
+    pub fn write(layout: &DynLayout, data: &[u8]) -> Result<Vec<u8>, FileError> {
+        let count = match layout.size {
+            0 => 0,
+            size => {
+                assert!(
+                    data.len().is_multiple_of(size),
+                    "Data ({} bytes) isn't a whole number of {} ({size} bytes)",
+                    data.len(),
+                    layout.name
+                );
+                data.len() / size
+            }
+        };
+        let mut layout_bytes = Vec::new();
+        write_layout(&mut layout_bytes, layout);
+        let data_offset = (HEADER_SIZE + layout_bytes.len()).next_multiple_of(DATA_ALIGN);
+        let to_u32 = |value: usize| u32::try_from(value).map_err(|_| FileError::TooLarge);
+
+        let mut out = Vec::with_capacity(data_offset + data.len());
+        out.extend_from_slice(&FILE_MAGIC);
+        out.extend_from_slice(&FILE_VERSION.to_le_bytes());
+        out.push(NATIVE_ENDIANNESS);
+        out.push(0);
+        out.extend_from_slice(&to_u32(count)?.to_le_bytes());
+        out.extend_from_slice(&xxh64(&layout_bytes, 0).to_le_bytes());
+        out.extend_from_slice(&to_u32(layout_bytes.len())?.to_le_bytes());
+        out.extend_from_slice(&to_u32(data_offset)?.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        out.extend_from_slice(&checksum(&layout_bytes, data).to_le_bytes());
+        out.extend_from_slice(&layout_bytes);
+        out.resize(data_offset, 0);
+        out.extend_from_slice(data);
+        Ok(out)
+    }
+
+    /// Writes structs that share a layout as one file. Panics if `structs` is empty or the layouts differ.
+    pub fn write_structs(structs: &[DynStruct]) -> Result<Vec<u8>, FileError> {
+        let layout = &structs.first().expect("Can't write an empty file").layout;
+        let mut data = Vec::with_capacity(layout.size * structs.len());
+        for dyn_struct in structs {
+            assert!(
+                dyn_struct.layout == *layout,
+                "Can't write structs with different layouts ({} and {})",
+                layout.name,
+                dyn_struct.layout.name
+            );
+            data.extend_from_slice(&dyn_struct.data);
+        }
+        Self::write(layout, &data)
+    }
+
+    /// Checks the header and checksum and decodes the layout. The data isn't copied.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, FileError> {
+        let truncated = |expected: usize| FileError::Truncated {
+            expected,
+            len: bytes.len(),
+        };
+        if bytes.len() < FILE_MAGIC.len() || bytes[..FILE_MAGIC.len()] != FILE_MAGIC {
+            return Err(FileError::BadMagic);
+        }
+        if bytes.len() < HEADER_SIZE {
+            return Err(truncated(HEADER_SIZE));
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+
+        let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+        if version != FILE_VERSION {
+            return Err(FileError::UnsupportedVersion(version));
+        }
+        if bytes[10] != NATIVE_ENDIANNESS {
+            return Err(FileError::EndiannessMismatch);
+        }
+        let count = u32_at(12);
+        let layout_hash = u64_at(16);
+        let layout_len = u32_at(24);
+        let data_offset = u32_at(28);
+        let data_len = usize::try_from(u64_at(32)).map_err(|_| FileError::CorruptLayout)?;
+
+        let layout_end = HEADER_SIZE + layout_len;
+        if data_offset < layout_end {
+            return Err(FileError::CorruptLayout);
+        }
+        let end = data_offset
+            .checked_add(data_len)
+            .ok_or(FileError::CorruptLayout)?;
+        if bytes.len() < end {
+            return Err(truncated(end));
+        }
+        let layout_bytes = &bytes[HEADER_SIZE..layout_end];
+        let data = &bytes[data_offset..end];
+        if checksum(layout_bytes, data) != u64_at(40) {
+            return Err(FileError::ChecksumMismatch);
+        }
+        if xxh64(layout_bytes, 0) != layout_hash {
+            return Err(FileError::LayoutHashMismatch);
+        }
+
+        let mut reader = Reader {
+            bytes: layout_bytes,
+            pos: 0,
+        };
+        let layout = read_layout(&mut reader, 0, 0)?;
+        if reader.pos != layout_bytes.len() || layout.size.checked_mul(count) != Some(data_len) {
+            return Err(FileError::CorruptLayout);
+        }
+        Ok(DynFile {
+            layout: Arc::new(layout),
+            data,
+        })
+    }
+
+    /// Number of structs in the file
+    pub fn len(&self) -> usize {
+        match self.layout.size {
+            0 => 0,
+            size => self.data.len() / size,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Errors if the file's layout isn't `layout`, for loading data saved from a known Rust type.
+    pub fn expect_layout(&self, layout: &DynLayout) -> Result<(), FileError> {
+        if *self.layout != *layout {
+            return Err(FileError::LayoutMismatch {
+                expected: layout.name.clone(),
+                found: self.layout.name.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// View of the struct at `index`. Panics if out of bounds.
+    pub fn get(&self, index: usize) -> DynView<'_> {
+        let size = self.layout.size;
+        DynView::new(&self.data[index * size..(index + 1) * size], &self.layout)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = DynView<'_>> {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    /// Copies every struct out of the file
+    pub fn to_dyn_structs(&self) -> Vec<DynStruct> {
+        self.iter().map(|view| view.to_dyn_struct()).collect()
+    }
+}
+
+impl DynStruct {
+    /// Saves the struct with its layout, see `DynFile`
+    pub fn to_file_bytes(&self) -> Result<Vec<u8>, FileError> {
+        DynFile::write(&self.layout, &self.data)
+    }
+
+    /// Loads a file holding exactly one struct
+    pub fn from_file_bytes(bytes: &[u8]) -> Result<DynStruct, FileError> {
+        let file = DynFile::parse(bytes)?;
+        if file.len() != 1 {
+            return Err(FileError::CountMismatch {
+                expected: 1,
+                count: file.len(),
+            });
+        }
+        Ok(file.get(0).to_dyn_struct())
+    }
+}
+
+fn checksum(layout: &[u8], data: &[u8]) -> u64 {
+    let mut hasher = Xxh64::new(0);
+    hasher.update(layout);
+    hasher.update(data);
+    hasher.digest()
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_varint(out, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// name, size, field count, then for each field its name, absolute offset and type tag (the index in `LEAF_TYPES`,
+/// or `STRUCT_TAG`/`ARRAY_TAG` followed by the nested layout)
+pub(crate) fn write_layout(out: &mut Vec<u8>, layout: &DynLayout) {
+    write_str(out, &layout.name);
+    write_varint(out, layout.size as u64);
+    write_varint(out, layout.fields.len() as u64);
+    for (name, field) in &layout.fields {
+        write_str(out, name);
+        write_varint(out, field.offset as u64);
+        match &field.ty {
+            BaseType::Struct(layout) => {
+                out.push(STRUCT_TAG);
+                write_layout(out, layout);
+            }
+            BaseType::Array(layout) => {
+                out.push(ARRAY_TAG);
+                write_layout(out, layout);
+            }
+            ty => {
+                let tag = LEAF_TYPES.iter().position(|leaf| leaf == ty).unwrap();
+                out.push(tag as u8);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], FileError> {
+        let end = self.pos.checked_add(len).ok_or(FileError::CorruptLayout)?;
+        let bytes = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(FileError::CorruptLayout)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<usize, FileError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return usize::try_from(value).map_err(|_| FileError::CorruptLayout);
+            }
+        }
+        Err(FileError::CorruptLayout)
+    }
+
+    fn str(&mut self) -> Result<&'a str, FileError> {
+        let len = self.varint()?;
+        std::str::from_utf8(self.take(len)?).map_err(|_| FileError::CorruptLayout)
+    }
+}
+
+/// Reads a layout written by `write_layout` that starts at `start`. Every field must lie within the layout.
+fn read_layout(reader: &mut Reader, depth: usize, start: usize) -> Result<DynLayout, FileError> {
+    if depth > MAX_DEPTH {
+        return Err(FileError::CorruptLayout);
+    }
+    let name = reader.str()?;
+    let layout_size = reader.varint()?;
+    let end = start
+        .checked_add(layout_size)
+        .filter(|end| *end <= u32::MAX as usize)
+        .ok_or(FileError::CorruptLayout)?;
+    let field_count = reader.varint()?;
+    // Every field takes at least 3 bytes, don't trust the count for the allocation
+    let mut fields = Vec::with_capacity(field_count.min(reader.bytes.len() / 3));
+    for _ in 0..field_count {
+        let field_name = reader.str()?.to_string();
+        let offset = reader.varint()?;
+        let ty = match reader.take(1)?[0] {
+            STRUCT_TAG => BaseType::Struct(Arc::new(read_layout(reader, depth + 1, offset)?)),
+            ARRAY_TAG => BaseType::Array(Arc::new(read_layout(reader, depth + 1, offset)?)),
+            tag => LEAF_TYPES
+                .get(tag as usize)
+                .cloned()
+                .ok_or(FileError::CorruptLayout)?,
+        };
+        if offset < start || offset.saturating_add(ty.size_of()) > end {
+            return Err(FileError::CorruptLayout);
+        }
+        fields.push((
+            field_name,
+            DynField {
+                offset: offset as u32,
+                ty,
+            },
+        ));
+    }
+    Ok(DynLayout::new(name, layout_size, fields))
+}
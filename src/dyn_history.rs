@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+
+use bytemuck::{bytes_of, Pod, Zeroable};
+
+use crate::{dyn_struct::DynStruct, tracked_dyn_struct::TrackedDynStruct};
+
+/// A struct `DynHistory` can record edits of. For `TrackedDynStruct` every write, including undo and redo, marks the
+/// written range as changed so the GPU copy follows.
+pub trait HistoryTarget {
+    fn dyn_struct(&self) -> &DynStruct;
+    fn write_bytes(&mut self, offset: usize, bytes: &[u8]);
+}
+
+impl HistoryTarget for DynStruct {
+    fn dyn_struct(&self) -> &DynStruct {
+        self
+    }
+
+    fn write_bytes(&mut self, offset: usize, bytes: &[u8]) {
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+impl HistoryTarget for TrackedDynStruct {
+    fn dyn_struct(&self) -> &DynStruct {
+        &self.dyn_struct
+    }
+
+    fn write_bytes(&mut self, offset: usize, bytes: &[u8]) {
+        self.dyn_struct.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        self.count_marked_bytes(bytes.len());
+        self.mark_blocks(self.stride_blocks(offset, bytes.len()));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Edit {
+    offset: usize,
+    before: Vec<u8>,
+    after: Vec<u8>,
+}
+
+/// Edits undone and redone together
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Transaction {
+    edits: Vec<Edit>,
+}
+
+impl Transaction {
+    fn memory(&self) -> usize {
+        self.edits
+            .iter()
+            .map(|edit| edit.before.len() + edit.after.len())
+            .sum()
+    }
+}
+
+/// Records the bytes before and after every edit made through it so they can be undone and redone.
+/// Edits made outside a `begin`/`commit` pair are their own transaction.
+///
+/// let mut history = DynHistory::new(material);
+/// history.begin();
+/// history.set(&["base_color"], vec4(1.0, 0.0, 0.0, 1.0));
+/// history.set(&["roughness"], 0.5f32);
+/// history.commit();
+/// history.undo();
+#[derive(Clone, Debug)]
+pub struct DynHistory<S: HistoryTarget> {
+    target: S,
+    undo: VecDeque<Transaction>,
+    redo: Vec<Transaction>,
+    /// Transaction being built and, for each nested `begin`, how many edits it had when that `begin` was called
+    open: Option<(Transaction, Vec<usize>)>,
+    memory_cap: usize,
+    memory: usize,
+}
+
+impl<S: HistoryTarget> DynHistory<S> {
+    pub fn new(target: S) -> Self {
+        DynHistory {
+            target,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            open: None,
+            memory_cap: usize::MAX,
+            memory: 0,
+        }
+    }
+
+    /// Limit the bytes kept for undo and redo, the oldest transactions are dropped first.
+    /// An open transaction is never dropped, the cap is enforced once it is committed.
+    pub fn with_memory_cap(mut self, memory_cap: usize) -> Self {
+        self.memory_cap = memory_cap;
+        self.enforce_memory_cap();
+        self
+    }
+
+    #[inline(always)]
+    pub fn target(&self) -> &S {
+        &self.target
+    }
+
+    /// Edits made through this aren't recorded
+    #[inline(always)]
+    pub fn target_mut(&mut self) -> &mut S {
+        &mut self.target
+    }
+
+    pub fn into_inner(self) -> S {
+        self.target
+    }
+
+    /// Bytes of before and after data kept for undo and redo
+    #[inline(always)]
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    /// Writes `value` to the field at `path`. Returns None if the path doesn't exist.
+    pub fn set<T: Pod + Zeroable>(&mut self, path: &[&str], value: T) -> Option<()> {
+        let field = self.target.dyn_struct().layout.get_path(path)?;
+        debug_assert_eq!(size_of::<T>(), field.ty.size_of());
+        self.set_raw(field.offset as usize, value);
+        Some(())
+    }
+
+    pub fn set_raw<T: Pod + Zeroable>(&mut self, offset: usize, value: T) {
+        self.set_bytes(offset, bytes_of(&value));
+    }
+
+    /// Writes `bytes` at `offset` and records the edit. Writes that don't change anything aren't recorded.
+    pub fn set_bytes(&mut self, offset: usize, bytes: &[u8]) {
+        let before = &self.target.dyn_struct().data[offset..offset + bytes.len()];
+        if before == bytes {
+            return;
+        }
+        let edit = Edit {
+            offset,
+            before: before.to_vec(),
+            after: bytes.to_vec(),
+        };
+        self.target.write_bytes(offset, bytes);
+
+        // New edits invalidate everything that was undone
+        self.memory -= self.redo.iter().map(Transaction::memory).sum::<usize>();
+        self.redo.clear();
+        self.memory += edit.before.len() + edit.after.len();
+        match &mut self.open {
+            Some((transaction, _)) => transaction.edits.push(edit),
+            None => {
+                self.undo.push_back(Transaction { edits: vec![edit] });
+                self.enforce_memory_cap();
+            }
+        }
+    }
+
+    /// Groups every edit until the matching `commit` into one transaction. Can be nested, only the outermost
+    /// `commit` ends the transaction.
+    pub fn begin(&mut self) {
+        match &mut self.open {
+            Some((transaction, marks)) => marks.push(transaction.edits.len()),
+            None => self.open = Some((Transaction::default(), vec![0])),
+        }
+    }
+
+    /// Ends the innermost `begin`. Panics if there isn't one.
+    pub fn commit(&mut self) {
+        let (transaction, mut marks) = self.open.take().expect("commit called without begin");
+        marks.pop();
+        if !marks.is_empty() {
+            self.open = Some((transaction, marks));
+        } else if !transaction.edits.is_empty() {
+            self.undo.push_back(transaction);
+            self.enforce_memory_cap();
+        }
+    }
+
+    /// Reverts the edits made since the innermost `begin` and ends it. Edits of outer levels are kept.
+    /// Panics if there isn't one.
+    pub fn rollback(&mut self) {
+        let (mut transaction, mut marks) = self.open.take().expect("rollback called without begin");
+        let mark = marks.pop().unwrap();
+        for edit in transaction.edits.drain(mark..).rev() {
+            self.memory -= edit.before.len() + edit.after.len();
+            self.target.write_bytes(edit.offset, &edit.before);
+        }
+        // The outermost mark is 0, so rolling it back leaves nothing to keep
+        if !marks.is_empty() {
+            self.open = Some((transaction, marks));
+        }
+    }
+
+    #[inline(always)]
+    pub fn in_transaction(&self) -> bool {
+        self.open.is_some()
+    }
+
+    #[inline(always)]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Reverts the last transaction. Returns false if there was nothing to undo.
+    /// Panics if called during a transaction.
+    pub fn undo(&mut self) -> bool {
+        assert!(self.open.is_none(), "Can't undo during a transaction");
+        let Some(transaction) = self.undo.pop_back() else {
+            return false;
+        };
+        for edit in transaction.edits.iter().rev() {
+            self.target.write_bytes(edit.offset, &edit.before);
+        }
+        self.redo.push(transaction);
+        true
+    }
+
+    /// Reapplies the last undone transaction. Returns false if there was nothing to redo.
+    /// Panics if called during a transaction.
+    pub fn redo(&mut self) -> bool {
+        assert!(self.open.is_none(), "Can't redo during a transaction");
+        let Some(transaction) = self.redo.pop() else {
+            return false;
+        };
+        for edit in &transaction.edits {
+            self.target.write_bytes(edit.offset, &edit.after);
+        }
+        self.undo.push_back(transaction);
+        true
+    }
+
+    /// Forgets every recorded edit
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.memory = self
+            .open
+            .as_ref()
+            .map(|(transaction, _)| transaction.memory())
+            .unwrap_or(0);
+    }
+
+    fn enforce_memory_cap(&mut self) {
+        // Drop redo first since it is the least likely to be used, then the oldest undo
+        while self.memory > self.memory_cap {
+            let transaction = if !self.redo.is_empty() {
+                self.redo.remove(0)
+            } else if let Some(transaction) = self.undo.pop_front() {
+                transaction
+            } else {
+                break;
+            };
+            self.memory -= transaction.memory();
+        }
+    }
+}
@@ -1,7 +1,8 @@
 pub use dyn_pod_struct_derive::DynLayout;
 use std::{
     fmt::{self, Display},
-    sync::Arc,
+    ops::Range,
+    sync::{Arc, OnceLock},
 };
 
 use difference::{Changeset, Difference};
@@ -20,6 +21,23 @@ pub struct DynLayout {
     pub fields_hash: FxHashMap<String, DynField>,
     /// Size of this struct in bytes
     pub size: usize,
+    /// See `LayoutCache`
+    pub cache: LayoutCache,
+}
+
+/// `DynLayout::leaves` and `DynLayout::layout_hash`, built on first use. Always compares equal so it doesn't affect
+/// comparing layouts. `append_type` clears it, call `DynLayout::invalidate_cache` after editing the other fields
+/// directly.
+#[derive(Clone, Debug, Default)]
+pub struct LayoutCache {
+    pub(crate) leaves: OnceLock<Vec<(String, DynField)>>,
+    pub(crate) hash: OnceLock<u64>,
+}
+
+impl PartialEq for LayoutCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
 }
 
 impl std::hash::Hash for DynLayout {
@@ -79,7 +97,7 @@ pub fn diff_display<T: Display, U: Display>(a: T, b: U) {
                                 _ => (),
                             }
                         }
-                        writeln!(t, "").unwrap();
+                        writeln!(t).unwrap();
                     }
                     _ => {
                         t.fg(term::color::BRIGHT_GREEN).unwrap();
@@ -108,6 +126,7 @@ impl DynLayout {
             fields,
             fields_hash: field_hash,
             size,
+            cache: LayoutCache::default(),
         }
     }
 
@@ -121,6 +140,7 @@ impl DynLayout {
         self.size += new_field.ty.size_of();
         self.fields.push((name.to_string(), new_field.clone()));
         self.fields_hash.insert(name.to_string(), new_field);
+        self.invalidate_cache();
     }
 
     /// Append type to end of layout. Assumes no padding between last type and the one being added.
@@ -147,6 +167,55 @@ impl DynLayout {
         self.size += new_field.ty.size_of();
         self.fields.push((name.to_string(), new_field.clone()));
         self.fields_hash.insert(name.to_string(), new_field);
+        self.invalidate_cache();
+    }
+
+    /// Creates the layout of a fixed size array starting at `offset` with elements `stride` bytes apart, for use with
+    /// `BaseType::Array`. Elements are named by their index so they can be accessed with paths like ["lights", "2"].
+    pub fn new_array(element: BaseType, len: usize, stride: usize, offset: u32) -> DynLayout {
+        let fields = (0..len)
+            .map(|i| {
+                let offset = offset + (i * stride) as u32;
+                let ty = element.rebased(offset);
+                (i.to_string(), DynField { offset, ty })
+            })
+            .collect();
+        let name = format!("[{}; {len}]", element.type_name());
+        DynLayout::new(&name, stride * len, fields)
+    }
+
+    /// Distance in bytes between elements if this is the layout of an array.
+    pub fn array_stride(&self) -> usize {
+        if self.fields.is_empty() {
+            0
+        } else {
+            self.size / self.fields.len()
+        }
+    }
+
+    /// Returns a copy of this layout moved so that it starts at `offset`.
+    /// Struct fields hold absolute offsets all the way down the hierarchy, so this recursively updates nested layouts too.
+    /// The start of the layout is taken to be the offset of its first field.
+    pub fn rebased(&self, offset: u32) -> DynLayout {
+        let start = self.fields.first().map(|(_, f)| f.offset).unwrap_or(0);
+        self.shifted(offset as i64 - start as i64)
+    }
+
+    fn shifted(&self, delta: i64) -> DynLayout {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, field)| {
+                let ty = match &field.ty {
+                    BaseType::Struct(layout) => BaseType::Struct(Arc::new(layout.shifted(delta))),
+                    BaseType::Array(layout) => BaseType::Array(Arc::new(layout.shifted(delta))),
+                    ty => ty.clone(),
+                };
+                let offset = (field.offset as i64 + delta) as u32;
+                (name.clone(), DynField { offset, ty })
+            })
+            .collect();
+        DynLayout::new(&self.name, self.size, fields)
     }
 
     pub fn format_with_offsets(&self, depth: usize, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -164,14 +233,11 @@ impl DynLayout {
             let padding = " ".repeat((depth + 1) * 4);
             let size = field.ty.size_of();
             let offset = field.offset;
-            if let BaseType::Struct(layout) = &field.ty {
+            if let Some(layout) = field.ty.layout() {
                 write!(f, "{size:>6} {offset:>6}  {padding}{field_name}: ")?;
                 layout.format_with_offsets(depth + 1, f)?;
             } else {
-                let mut ty_name = format!("{:?}", &field.ty);
-                if field.ty.rust_base_type() {
-                    ty_name = ty_name.to_lowercase();
-                }
+                let ty_name = field.ty.type_name();
                 writeln!(f, "{size:>6} {offset:>6}  {padding}{field_name}: {ty_name}")?;
             }
         }
@@ -184,14 +250,14 @@ impl DynLayout {
         let mut layout = self;
         let mut field = None;
 
-        let last = path.len() - 1;
+        let last = path.len().checked_sub(1)?;
 
         for (i, s) in path.iter().enumerate() {
             field = layout.fields_hash.get(*s);
-            if let BaseType::Struct(field_layout) = &field?.ty {
+            if let Some(field_layout) = field?.ty.layout() {
                 layout = field_layout;
             } else if last != i {
-                // If this isn't the end of the path, a struct is expected.
+                // If this isn't the end of the path, a struct or array is expected.
                 return None;
             }
         }
@@ -202,6 +268,72 @@ impl DynLayout {
             None
         }
     }
+
+    /// Largest alignment of any field, 1 for an empty layout
+    pub fn align(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|(_, field)| field.ty.align_of())
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// All fields that aren't structs or arrays, recursively and in struct order, with their path separated by `.`
+    /// (e.g. "nested.a" or "lights.0.color"). Built on first use and cached, see `LayoutCache`.
+    pub fn leaves(&self) -> &[(String, DynField)] {
+        fn collect(layout: &DynLayout, prefix: &str, out: &mut Vec<(String, DynField)>) {
+            for (name, field) in &layout.fields {
+                let path = join_path(prefix, name);
+                if let Some(layout) = field.ty.layout() {
+                    collect(layout, &path, out);
+                } else {
+                    out.push((path, field.clone()));
+                }
+            }
+        }
+        self.cache.leaves.get_or_init(|| {
+            let mut leaves = Vec::new();
+            collect(self, "", &mut leaves);
+            leaves
+        })
+    }
+
+    /// Clears the cached `leaves` and `layout_hash`, needed after editing the fields of the layout directly
+    pub fn invalidate_cache(&mut self) {
+        self.cache = LayoutCache::default();
+    }
+
+    /// Byte ranges (absolute offsets) not covered by any field, including trailing padding at the end of the struct.
+    pub fn padding(&self) -> Vec<Range<usize>> {
+        let start = self.fields.first().map(|(_, f)| f.offset).unwrap_or(0) as usize;
+        let mut leaves = self
+            .leaves()
+            .iter()
+            .map(|(_, field)| field.offset as usize..field.offset as usize + field.ty.size_of())
+            .collect::<Vec<_>>();
+        leaves.sort_by_key(|range| range.start);
+        let mut padding = Vec::new();
+        let mut end = start;
+        for range in leaves {
+            if range.start > end {
+                padding.push(end..range.start);
+            }
+            end = end.max(range.end);
+        }
+        if start + self.size > end {
+            padding.push(end..start + self.size);
+        }
+        padding
+    }
+}
+
+/// Joins field names into a path separated by `.`
+pub(crate) fn join_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}.{name}")
+    }
 }
 
 pub trait HasDynLayout {
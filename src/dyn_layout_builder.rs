@@ -0,0 +1,146 @@
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    base_type::BaseType,
+    dyn_layout::DynLayout,
+    dyn_struct::DynField,
+    packing::{round_up, PackingRules},
+};
+
+/// Builds a `DynLayout` at runtime, placing each field at the offset the chosen `PackingRules` require.
+/// Unlike `DynLayout::append_type` this inserts padding where needed and sets up absolute offsets for nested structs.
+///
+/// let layout = DynLayoutBuilder::new("Material", PackingRules::Std430)
+///     .with_field("roughness", BaseType::F32)
+///     .with_field("albedo", BaseType::Vec3) // Placed at offset 16
+///     .build();
+#[derive(Clone, Debug)]
+pub struct DynLayoutBuilder {
+    name: String,
+    rules: PackingRules,
+    fields: Vec<(String, DynField)>,
+    /// End of the last field in bytes
+    offset: usize,
+    /// Largest field alignment so far
+    align: usize,
+    padding: Vec<Range<usize>>,
+}
+
+impl DynLayoutBuilder {
+    pub fn new(name: &str, rules: PackingRules) -> Self {
+        DynLayoutBuilder {
+            name: name.to_string(),
+            rules,
+            fields: Vec::new(),
+            offset: 0,
+            align: 1,
+            padding: Vec::new(),
+        }
+    }
+
+    pub fn rules(&self) -> PackingRules {
+        self.rules
+    }
+
+    /// Append a field at the next offset allowed by the packing rules.
+    /// Nested struct layouts are copied and moved to their new offset. Their fields are assumed to already follow the
+    /// packing rules (build them with a `DynLayoutBuilder` using the same rules).
+    /// Panics if the rust layout of `ty` can't match the packing rules (e.g. `Mat3` with std140 or std430, or an array
+    /// with the wrong stride).
+    pub fn add_field(&mut self, name: &str, ty: BaseType) -> &mut Self {
+        if !self.rules.is_representable(&ty) {
+            panic!(
+                "{} field {name:?} can't be represented with {:?} packing rules, its rust layout doesn't match",
+                ty.type_name(),
+                self.rules
+            )
+        }
+        let align = self.rules.align_of(&ty);
+        let size = self.rules.size_of(&ty);
+        let offset = self.next_offset(&ty);
+        if offset > self.offset {
+            self.padding.push(self.offset..offset);
+        }
+        let ty_size = ty.size_of();
+        if size > ty_size {
+            // Trailing padding of a nested struct or array
+            self.padding.push(offset + ty_size..offset + size);
+        }
+        self.fields.push((
+            name.to_string(),
+            DynField {
+                offset: offset as u32,
+                ty: ty.rebased(offset as u32),
+            },
+        ));
+        self.offset = offset + size;
+        self.align = self.align.max(align);
+        self
+    }
+
+    /// Append a fixed size array using the element stride required by the packing rules.
+    pub fn add_array(&mut self, name: &str, element: BaseType, len: usize) -> &mut Self {
+        let stride = self.rules.array_stride(&element);
+        let layout = DynLayout::new_array(element, len, stride, 0);
+        self.add_field(name, BaseType::Array(Arc::new(layout)))
+    }
+
+    /// Same as `add_array` but for chaining.
+    pub fn with_array(mut self, name: &str, element: BaseType, len: usize) -> Self {
+        self.add_array(name, element, len);
+        self
+    }
+
+    /// Offset `ty` would be placed at if it was added next.
+    pub fn next_offset(&self, ty: &BaseType) -> usize {
+        let size = self.rules.size_of(ty);
+        let offset = round_up(self.offset, self.rules.align_of(ty));
+        if self.rules == PackingRules::HlslCbuffer
+            && size > 0
+            && offset / 16 != (offset + size - 1) / 16
+        {
+            // Fields can't straddle a 16 byte register
+            round_up(offset, 16)
+        } else {
+            offset
+        }
+    }
+
+    /// End of the last field added, before the struct is rounded up to its alignment.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Same as `add_field` but for chaining.
+    pub fn with_field(mut self, name: &str, ty: BaseType) -> Self {
+        self.add_field(name, ty);
+        self
+    }
+
+    /// Alignment of the struct being built.
+    pub fn align(&self) -> usize {
+        match self.rules {
+            PackingRules::Std140 | PackingRules::HlslCbuffer => round_up(self.align, 16),
+            _ => self.align,
+        }
+    }
+
+    /// Size of the struct being built, rounded up to its alignment.
+    pub fn size(&self) -> usize {
+        round_up(self.offset, self.align())
+    }
+
+    /// Byte ranges of padding that were inserted, including the trailing padding at the end of the struct.
+    pub fn padding(&self) -> Vec<Range<usize>> {
+        let mut padding = self.padding.clone();
+        let size = self.size();
+        if size > self.offset {
+            padding.push(self.offset..size);
+        }
+        padding
+    }
+
+    pub fn build(&self) -> Arc<DynLayout> {
+        Arc::new(DynLayout::new(&self.name, self.size(), self.fields.clone()))
+    }
+}
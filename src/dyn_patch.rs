@@ -0,0 +1,426 @@
+use std::fmt::{self, Display};
+
+use xxhash_rust::xxh64::xxh64;
+
+use crate::{
+    dyn_file::write_layout, dyn_layout::DynLayout, dyn_struct::DynStruct,
+    tracked_dyn_struct::TrackedDynStruct,
+};
+
+const XOR_FLAG: u8 = 1;
+const RLE_FLAG: u8 = 2;
+
+/// Changed byte ranges of a `DynStruct` that can be encoded, sent to another process or saved for replay and applied
+/// with `DynStruct::apply_patch`.
+///
+/// let patch = DynPatch::from_changes(&instance);
+/// let bytes = patch.encode(PatchEncoding::default(), None)?;
+/// remote.apply_patch(&DynPatch::decode(&bytes, None)?)?;
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DynPatch {
+    /// `DynLayout::layout_hash` of the layout the patch was made from
+    pub layout_hash: u64,
+    /// Size of the struct in bytes
+    pub size: usize,
+    /// Sorted, non overlapping ranges
+    pub ranges: Vec<PatchRange>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PatchRange {
+    pub offset: usize,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PatchEncoding {
+    /// XOR the data with the previous value of the struct, so unchanged bytes become zeros. The receiver needs the
+    /// same previous value to decode.
+    pub xor: bool,
+    /// Run length encode the data. Works best combined with `xor`.
+    pub rle: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatchError {
+    /// The patch was made from a different layout
+    LayoutMismatch { expected: u64, found: u64 },
+    /// The patch is for a struct of a different size
+    SizeMismatch { expected: usize, found: usize },
+    /// A range doesn't fit in the struct
+    OutOfBounds {
+        offset: usize,
+        len: usize,
+        size: usize,
+    },
+    /// XOR encoding or decoding needs the previous value of the struct but none was given, or it has the wrong size
+    MissingBase,
+    /// A range starts before the end of the previous one, ranges must be sorted and not overlap
+    UnorderedRange { offset: usize },
+    /// The encoded patch ended early or contains invalid data
+    Corrupt,
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::LayoutMismatch { expected, found } => write!(
+                f,
+                "patch layout hash {found:#018x} doesn't match {expected:#018x}"
+            ),
+            PatchError::SizeMismatch { expected, found } => {
+                write!(
+                    f,
+                    "patch is for {found} bytes but struct is {expected} bytes"
+                )
+            }
+            PatchError::OutOfBounds { offset, len, size } => write!(
+                f,
+                "patch range {offset}..{} is outside of the {size} byte struct",
+                offset + len
+            ),
+            PatchError::MissingBase => {
+                write!(
+                    f,
+                    "XOR encoded patches need the previous value of the struct"
+                )
+            }
+            PatchError::UnorderedRange { offset } => write!(
+                f,
+                "patch range at {offset} overlaps or comes before the previous range"
+            ),
+            PatchError::Corrupt => write!(f, "encoded patch is truncated or corrupt"),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl DynLayout {
+    /// Hash of the layout (names, offsets, types and size) used to check that data is interpreted with the same layout
+    /// it was written with. xxh64 (seed 0) of the layout encoding used by `dyn_file`, so it is stable across
+    /// processes, platforms and compiler versions and matches the layout hash in a file's header.
+    /// Computed on first use and cached, see `LayoutCache`.
+    pub fn layout_hash(&self) -> u64 {
+        *self.cache.hash.get_or_init(|| {
+            let mut bytes = Vec::new();
+            write_layout(&mut bytes, self);
+            xxh64(&bytes, 0)
+        })
+    }
+}
+
+impl DynPatch {
+    /// Patch of the bytes in `ranges` of `dyn_struct`. Ranges must be sorted and not overlap.
+    pub fn from_ranges(
+        dyn_struct: &DynStruct,
+        ranges: impl IntoIterator<Item = std::ops::Range<usize>>,
+    ) -> Self {
+        DynPatch {
+            layout_hash: dyn_struct.layout.layout_hash(),
+            size: dyn_struct.data.len(),
+            ranges: ranges
+                .into_iter()
+                .map(|range| PatchRange {
+                    offset: range.start,
+                    data: dyn_struct.data[range].to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Patch of the update stride blocks marked as changed in `tracked`
+    pub fn from_changes(tracked: &TrackedDynStruct) -> Self {
+        let stride = tracked.update_stride();
+        let size = tracked.dyn_struct.data.len();
+        let ranges = tracked
+            .update_bitmask
+            .ranges()
+            .map(|range| range.start * stride..(range.end * stride).min(size));
+        Self::from_ranges(&tracked.dyn_struct, ranges)
+    }
+
+    /// Patch of the bytes that differ between `old` and `new`, which must have the same layout
+    pub fn diff(old: &DynStruct, new: &DynStruct) -> Self {
+        assert_eq!(
+            old.data.len(),
+            new.data.len(),
+            "Structs have different sizes"
+        );
+        let mut ranges = Vec::new();
+        let mut start = None;
+        for (i, (a, b)) in old.data.iter().zip(&new.data).enumerate() {
+            match (a != b, start) {
+                (true, None) => start = Some(i),
+                (false, Some(s)) => {
+                    ranges.push(s..i);
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+        if let Some(s) = start {
+            ranges.push(s..new.data.len());
+        }
+        Self::from_ranges(new, ranges)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Encodes the patch as
+    /// `flags: u8, layout_hash: u64 LE, size: varint, range count: varint` then for every range
+    /// `gap since the end of the previous range: varint, len: varint, data`.
+    /// With `encoding.xor` the data is XORed with `previous`, the value of the whole struct before the patch.
+    /// With `encoding.rle` the data is a sequence of varint tokens, `len << 1` followed by `len` literal bytes or
+    /// `len << 1 | 1` followed by one byte repeated `len` times.
+    /// Errors if the ranges aren't sorted, overlap or don't fit in the struct, or if `encoding.xor` is set and
+    /// `previous` isn't the size of the struct.
+    pub fn encode(
+        &self,
+        encoding: PatchEncoding,
+        previous: Option<&[u8]>,
+    ) -> Result<Vec<u8>, PatchError> {
+        self.check_ranges(self.size)?;
+        let mut flags = 0;
+        if encoding.xor {
+            if previous.map(|previous| previous.len()) != Some(self.size) {
+                return Err(PatchError::MissingBase);
+            }
+            flags |= XOR_FLAG;
+        }
+        if encoding.rle {
+            flags |= RLE_FLAG;
+        }
+
+        let mut out = vec![flags];
+        out.extend_from_slice(&self.layout_hash.to_le_bytes());
+        write_varint(&mut out, self.size as u64);
+        write_varint(&mut out, self.ranges.len() as u64);
+        let mut end = 0;
+        let mut data = Vec::new();
+        for range in &self.ranges {
+            let len = range.data.len();
+            write_varint(&mut out, (range.offset - end) as u64);
+            write_varint(&mut out, len as u64);
+            end = range.offset + len;
+
+            data.clear();
+            data.extend_from_slice(&range.data);
+            if let (true, Some(previous)) = (encoding.xor, previous) {
+                for (byte, old) in data.iter_mut().zip(&previous[range.offset..end]) {
+                    *byte ^= old;
+                }
+            }
+            if encoding.rle {
+                write_rle(&mut out, &data);
+            } else {
+                out.extend_from_slice(&data);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Decodes a patch written by `encode`. `previous` must be the same value of the struct the patch was encoded
+    /// against if it was XOR encoded.
+    pub fn decode(bytes: &[u8], previous: Option<&[u8]>) -> Result<Self, PatchError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        let flags = reader.byte()?;
+        if flags & !(XOR_FLAG | RLE_FLAG) != 0 {
+            return Err(PatchError::Corrupt);
+        }
+        let layout_hash = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+        let size = reader.varint()? as usize;
+        let count = reader.varint()? as usize;
+        let previous = if flags & XOR_FLAG != 0 {
+            match previous {
+                Some(previous) if previous.len() == size => Some(previous),
+                _ => return Err(PatchError::MissingBase),
+            }
+        } else {
+            None
+        };
+
+        let mut ranges = Vec::with_capacity(count.min(bytes.len()));
+        let mut end = 0usize;
+        for _ in 0..count {
+            let offset = end
+                .checked_add(reader.varint()? as usize)
+                .ok_or(PatchError::Corrupt)?;
+            let len = reader.varint()? as usize;
+            end = offset.checked_add(len).ok_or(PatchError::Corrupt)?;
+            if end > size {
+                return Err(PatchError::OutOfBounds { offset, len, size });
+            }
+            let mut data = if flags & RLE_FLAG != 0 {
+                reader.rle(len)?
+            } else {
+                reader.take(len)?.to_vec()
+            };
+            if let Some(previous) = previous {
+                for (byte, old) in data.iter_mut().zip(&previous[offset..end]) {
+                    *byte ^= old;
+                }
+            }
+            ranges.push(PatchRange { offset, data });
+        }
+        if reader.pos != bytes.len() {
+            return Err(PatchError::Corrupt);
+        }
+        Ok(DynPatch {
+            layout_hash,
+            size,
+            ranges,
+        })
+    }
+
+    fn check(&self, dyn_struct: &DynStruct) -> Result<(), PatchError> {
+        let expected = dyn_struct.layout.layout_hash();
+        if self.layout_hash != expected {
+            return Err(PatchError::LayoutMismatch {
+                expected,
+                found: self.layout_hash,
+            });
+        }
+        let size = dyn_struct.data.len();
+        if self.size != size {
+            return Err(PatchError::SizeMismatch {
+                expected: size,
+                found: self.size,
+            });
+        }
+        self.check_ranges(size)
+    }
+
+    /// Ranges must be in increasing order, not overlap and fit in `size` bytes
+    fn check_ranges(&self, size: usize) -> Result<(), PatchError> {
+        let mut end = 0;
+        for range in &self.ranges {
+            let len = range.data.len();
+            if range.offset < end {
+                return Err(PatchError::UnorderedRange {
+                    offset: range.offset,
+                });
+            }
+            end = match range.offset.checked_add(len) {
+                Some(range_end) if range_end <= size => range_end,
+                _ => {
+                    return Err(PatchError::OutOfBounds {
+                        offset: range.offset,
+                        len,
+                        size,
+                    })
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+impl DynStruct {
+    /// Writes the ranges of `patch`. Nothing is written if the patch was made from a different layout or doesn't fit.
+    pub fn apply_patch(&mut self, patch: &DynPatch) -> Result<(), PatchError> {
+        patch.check(self)?;
+        for range in &patch.ranges {
+            self.data[range.offset..range.offset + range.data.len()].copy_from_slice(&range.data);
+        }
+        Ok(())
+    }
+}
+
+impl TrackedDynStruct {
+    /// `DynStruct::apply_patch` that marks the patched ranges as changed
+    pub fn apply_patch(&mut self, patch: &DynPatch) -> Result<(), PatchError> {
+        self.dyn_struct.apply_patch(patch)?;
+        for range in &patch.ranges {
+            self.mark_blocks(self.stride_blocks(range.offset, range.data.len()));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_rle(out: &mut Vec<u8>, data: &[u8]) {
+    // Runs shorter than this are cheaper as literals
+    const MIN_REPEAT: usize = 3;
+    let mut literal_start = 0;
+    let mut i = 0;
+    while i < data.len() {
+        let repeat = data[i..]
+            .iter()
+            .take_while(|byte| **byte == data[i])
+            .count();
+        if repeat >= MIN_REPEAT {
+            if literal_start < i {
+                write_varint(out, ((i - literal_start) as u64) << 1);
+                out.extend_from_slice(&data[literal_start..i]);
+            }
+            write_varint(out, (repeat as u64) << 1 | 1);
+            out.push(data[i]);
+            i += repeat;
+            literal_start = i;
+        } else {
+            i += repeat;
+        }
+    }
+    if literal_start < data.len() {
+        write_varint(out, ((data.len() - literal_start) as u64) << 1);
+        out.extend_from_slice(&data[literal_start..]);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], PatchError> {
+        let end = self.pos.checked_add(len).ok_or(PatchError::Corrupt)?;
+        let bytes = self.bytes.get(self.pos..end).ok_or(PatchError::Corrupt)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, PatchError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, PatchError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(PatchError::Corrupt)
+    }
+
+    fn rle(&mut self, len: usize) -> Result<Vec<u8>, PatchError> {
+        let mut data = Vec::with_capacity(len.min(self.bytes.len()));
+        while data.len() < len {
+            let token = self.varint()?;
+            let count = (token >> 1) as usize;
+            if count == 0 || count > len - data.len() {
+                return Err(PatchError::Corrupt);
+            }
+            if token & 1 == 1 {
+                let byte = self.byte()?;
+                data.resize(data.len() + count, byte);
+            } else {
+                data.extend_from_slice(self.take(count)?);
+            }
+        }
+        Ok(data)
+    }
+}
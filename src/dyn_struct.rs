@@ -5,7 +5,11 @@ use bevy_reflect::TypePath;
 
 use bytemuck::{bytes_of, Pod, Zeroable};
 
-use crate::{base_type::BaseType, dyn_layout::DynLayout};
+use crate::{
+    aligned_bytes::{AlignedBytes, MAX_ALIGN},
+    base_type::BaseType,
+    dyn_layout::DynLayout,
+};
 
 #[derive(Clone, Default, Debug, PartialEq, Hash)]
 pub struct DynField {
@@ -18,7 +22,8 @@ pub struct DynField {
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "bevy_reflect", derive(TypePath))]
 pub struct DynStruct {
-    pub data: Vec<u8>,
+    /// Aligned to `MAX_ALIGN`, so every field can be borrowed in place with `get`
+    pub data: AlignedBytes,
     pub layout: Arc<DynLayout>,
 }
 
@@ -28,6 +33,12 @@ impl DynStruct {
     /// Creating a layout can be slow, prefer creating a layout once and reusing.
     /// let layout = T::dyn_layout();
     pub fn new<T: Pod>(data: &T, layout: &Arc<DynLayout>) -> Self {
+        assert!(
+            align_of::<T>() <= MAX_ALIGN,
+            "{} is aligned to {} bytes, DynStruct data is only aligned to {MAX_ALIGN}",
+            type_name::<T>(),
+            align_of::<T>()
+        );
         if layout.size != size_of::<T>() {
             panic!(
                 "DynStruct layout does not match data length ({} != {}). Layout: {:?} T: {}",
@@ -38,12 +49,21 @@ impl DynStruct {
             )
         }
         DynStruct {
-            data: bytes_of(data).to_vec(),
+            data: AlignedBytes::from(bytes_of(data)),
             layout: layout.clone(),
         }
     }
 
-    pub fn from_bytes(data: Vec<u8>, layout: Arc<DynLayout>) -> Self {
+    /// Takes `Vec<u8>` or `AlignedBytes`, a `Vec` is copied into aligned storage
+    pub fn from_bytes(data: impl Into<AlignedBytes>, layout: Arc<DynLayout>) -> Self {
+        let data = data.into();
+        // The data is only aligned to MAX_ALIGN, fields aligned to more couldn't be borrowed in place
+        let align = layout.align();
+        assert!(
+            align <= MAX_ALIGN,
+            "Layout {:?} is aligned to {align} bytes, DynStruct data is only aligned to {MAX_ALIGN}",
+            layout.name
+        );
         let data_len = data.len();
         let layout_data_len = layout.size;
         if layout_data_len != data_len {
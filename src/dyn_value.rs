@@ -0,0 +1,458 @@
+use std::fmt::{self, Display};
+
+use half::f16;
+
+use crate::{
+    access::AccessError,
+    base_type::BaseType,
+    dyn_struct::{DynField, DynStruct},
+    packing::TypeShape,
+    tracked_dyn_struct::TrackedDynStruct,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScalarKind {
+    Unsigned,
+    Signed,
+    Float,
+    /// Unsigned normalized integer, decoded to a float in [0, 1]
+    Unorm,
+    /// Signed normalized integer, decoded to a float in [-1, 1]
+    Snorm,
+}
+
+/// One component of a decoded value, widened to the largest type of its kind
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scalar {
+    Unsigned(u128),
+    Signed(i128),
+    Float(f64),
+}
+
+/// Decoded value of a leaf field. `components` are in memory order, so matrices are column major and affines are
+/// their matrix columns followed by the translation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynValue {
+    pub ty: BaseType,
+    pub components: Vec<Scalar>,
+}
+
+impl BaseType {
+    /// Kind of the components of the type, None for `None`, structs and arrays
+    pub fn scalar_kind(&self) -> Option<ScalarKind> {
+        match self {
+            BaseType::U8
+            | BaseType::U16
+            | BaseType::U32
+            | BaseType::U64
+            | BaseType::U128
+            | BaseType::UVec2
+            | BaseType::UVec3
+            | BaseType::UVec4 => Some(ScalarKind::Unsigned),
+            BaseType::I8
+            | BaseType::I16
+            | BaseType::I32
+            | BaseType::I64
+            | BaseType::I128
+            | BaseType::IVec2
+            | BaseType::IVec3
+            | BaseType::IVec4 => Some(ScalarKind::Signed),
+            BaseType::Unorm8x4 | BaseType::Unorm16x2 => Some(ScalarKind::Unorm),
+            BaseType::Snorm8x4 | BaseType::Snorm16x2 => Some(ScalarKind::Snorm),
+            BaseType::None | BaseType::Struct(_) | BaseType::Array(_) => None,
+            _ => Some(ScalarKind::Float),
+        }
+    }
+
+    /// Size in bytes of one component and the number of components, None for `None`, structs and arrays
+    pub fn components(&self) -> Option<(usize, usize)> {
+        match self.shape() {
+            TypeShape::Scalar { size } => Some((size, 1)),
+            TypeShape::Vector {
+                component_size,
+                len,
+            }
+            | TypeShape::Packed {
+                component_size,
+                len,
+            } => Some((component_size, len)),
+            TypeShape::Matrix {
+                component_size,
+                columns,
+                rows,
+            } => Some((component_size, columns * rows)),
+            TypeShape::None | TypeShape::Struct(_) | TypeShape::Array(_) => None,
+        }
+    }
+}
+
+impl Scalar {
+    /// Decodes a component of `bytes.len()` bytes. Normalized components are decoded to `Float`.
+    pub fn read(kind: ScalarKind, bytes: &[u8]) -> Scalar {
+        let mut buf = [0; 16];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        match kind {
+            ScalarKind::Unsigned => Scalar::Unsigned(u128::from_le_bytes(buf)),
+            ScalarKind::Signed => {
+                // Sign extend from the size of the component
+                let shift = 128 - bytes.len() * 8;
+                Scalar::Signed(i128::from_le_bytes(buf) << shift >> shift)
+            }
+            ScalarKind::Float => match bytes.len() {
+                2 => Scalar::Float(f16::from_le_bytes([buf[0], buf[1]]).to_f64()),
+                4 => Scalar::Float(f32::from_le_bytes(buf[..4].try_into().unwrap()) as f64),
+                _ => Scalar::Float(f64::from_le_bytes(buf[..8].try_into().unwrap())),
+            },
+            ScalarKind::Unorm => {
+                let value = Scalar::read(ScalarKind::Unsigned, bytes).as_f64();
+                Scalar::Float(value / unorm_max(bytes.len()))
+            }
+            ScalarKind::Snorm => {
+                let value = Scalar::read(ScalarKind::Signed, bytes).as_f64();
+                Scalar::Float((value / snorm_max(bytes.len())).max(-1.0))
+            }
+        }
+    }
+
+    /// Writes the scalar as a component of `bytes.len()` bytes. Values that don't fit are truncated.
+    pub fn write(&self, bytes: &mut [u8]) {
+        let len = bytes.len();
+        match *self {
+            Scalar::Unsigned(value) => bytes.copy_from_slice(&value.to_le_bytes()[..len]),
+            Scalar::Signed(value) => bytes.copy_from_slice(&value.to_le_bytes()[..len]),
+            Scalar::Float(value) => match len {
+                2 => bytes.copy_from_slice(&f16::from_f64(value).to_le_bytes()),
+                4 => bytes.copy_from_slice(&(value as f32).to_le_bytes()),
+                _ => bytes.copy_from_slice(&value.to_le_bytes()),
+            },
+        }
+    }
+
+    /// Writes the scalar as a component of `kind`. Normalized components are clamped to their range and rounded to
+    /// the nearest step, other kinds are written like `write`.
+    pub fn write_as(&self, kind: ScalarKind, bytes: &mut [u8]) {
+        match kind {
+            ScalarKind::Unorm => {
+                let value = self.as_f64().clamp(0.0, 1.0) * unorm_max(bytes.len());
+                Scalar::Unsigned(value.round() as u128).write(bytes)
+            }
+            ScalarKind::Snorm => {
+                let value = self.as_f64().clamp(-1.0, 1.0) * snorm_max(bytes.len());
+                Scalar::Signed(value.round() as i128).write(bytes)
+            }
+            _ => self.write(bytes),
+        }
+    }
+
+    /// Parses one component of `size` bytes. Integers are range checked, f16 and f32 components must be finite at
+    /// their size if they are as f64 and normalized components must be in their range.
+    pub fn parse(kind: ScalarKind, size: usize, s: &str) -> Result<Scalar, String> {
+        let s = s.trim();
+        let bits = size as u32 * 8;
+        let out_of_range = || format!("{s} is out of range");
+        match kind {
+            ScalarKind::Float | ScalarKind::Unorm | ScalarKind::Snorm => {
+                let value: f64 = s.parse().map_err(|_| format!("{s:?} isn't a number"))?;
+                let fits = match (kind, size) {
+                    (ScalarKind::Unorm, _) => (0.0..=1.0).contains(&value),
+                    (ScalarKind::Snorm, _) => (-1.0..=1.0).contains(&value),
+                    (_, 2) => !value.is_finite() || f16::from_f64(value).is_finite(),
+                    (_, 4) => !value.is_finite() || (value as f32).is_finite(),
+                    _ => true,
+                };
+                if !fits {
+                    return Err(out_of_range());
+                }
+                Ok(Scalar::Float(value))
+            }
+            ScalarKind::Unsigned => {
+                let value: u128 = s.parse().map_err(|_| match s.parse::<i128>() {
+                    Ok(_) => out_of_range(),
+                    Err(_) => format!("{s:?} isn't an integer"),
+                })?;
+                if bits < 128 && value >> bits != 0 {
+                    return Err(out_of_range());
+                }
+                Ok(Scalar::Unsigned(value))
+            }
+            ScalarKind::Signed => {
+                let value: i128 = s.parse().map_err(|_| match s.parse::<u128>() {
+                    Ok(_) => out_of_range(),
+                    Err(_) => format!("{s:?} isn't an integer"),
+                })?;
+                let shifted = value >> (bits - 1);
+                if bits < 128 && shifted != 0 && shifted != -1 {
+                    return Err(out_of_range());
+                }
+                Ok(Scalar::Signed(value))
+            }
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Scalar::Unsigned(value) => value as f64,
+            Scalar::Signed(value) => value as f64,
+            Scalar::Float(value) => value,
+        }
+    }
+}
+
+/// Largest value of an unsigned normalized component of `size` bytes
+fn unorm_max(size: usize) -> f64 {
+    ((1u128 << (size * 8)) - 1) as f64
+}
+
+/// Largest value of a signed normalized component of `size` bytes
+fn snorm_max(size: usize) -> f64 {
+    ((1u128 << (size * 8 - 1)) - 1) as f64
+}
+
+impl DynValue {
+    /// Decodes a value of type `ty` from `bytes`. Returns None for `None`, structs and arrays.
+    pub fn read(ty: &BaseType, bytes: &[u8]) -> Option<DynValue> {
+        let kind = ty.scalar_kind()?;
+        let (component_size, count) = ty.components()?;
+        let components = bytes[..component_size * count]
+            .chunks_exact(component_size)
+            .map(|bytes| Scalar::read(kind, bytes))
+            .collect();
+        Some(DynValue {
+            ty: ty.clone(),
+            components,
+        })
+    }
+
+    /// Writes the value to `bytes`, which must be at least the size of the type
+    pub fn write(&self, bytes: &mut [u8]) {
+        let kind = self.ty.scalar_kind().unwrap_or(ScalarKind::Float);
+        let (component_size, _) = self.ty.components().unwrap_or((0, 0));
+        for (component, bytes) in self
+            .components
+            .iter()
+            .zip(bytes.chunks_exact_mut(component_size.max(1)))
+        {
+            component.write_as(kind, bytes);
+        }
+    }
+}
+
+impl DynValue {
+    /// Parses a value of type `ty` written as a scalar, a vector as `1, 2, 3` or `(1, 2, 3)`, or a matrix as its
+    /// columns `[(1, 0), (0, 1)]` or the flat column major components. Accepts everything `Display` writes.
+    /// Returns a description of the problem on error, None for `None`, structs and arrays.
+    pub fn parse(ty: &BaseType, s: &str) -> Option<Result<DynValue, String>> {
+        let kind = ty.scalar_kind()?;
+        let (component_size, count) = ty.components()?;
+        let s = s.trim();
+        let open = s.chars().filter(|c| matches!(c, '(' | '[')).count();
+        let close = s.chars().filter(|c| matches!(c, ')' | ']')).count();
+        if open != close {
+            return Some(Err("unbalanced brackets".to_string()));
+        }
+        let flat = s.replace(['(', ')', '[', ']'], "");
+        let parts = flat.split(',').collect::<Vec<_>>();
+        if parts.len() != count {
+            return Some(Err(format!(
+                "expected {count} component{}, found {}",
+                if count == 1 { "" } else { "s" },
+                parts.len()
+            )));
+        }
+        let components = parts
+            .into_iter()
+            .map(|part| Scalar::parse(kind, component_size, part))
+            .collect::<Result<Vec<_>, _>>();
+        Some(components.map(|components| DynValue {
+            ty: ty.clone(),
+            components,
+        }))
+    }
+}
+
+/// Error from `DynStruct::set_from_str`
+#[derive(Clone, Debug, PartialEq)]
+pub enum SetValueError {
+    Access(AccessError),
+    /// The string isn't a valid `expected`, or the field is a struct or array
+    Parse {
+        path: String,
+        expected: BaseType,
+        input: String,
+        reason: String,
+    },
+}
+
+impl Display for SetValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetValueError::Access(error) => write!(f, "{error}"),
+            SetValueError::Parse {
+                path,
+                expected,
+                input,
+                reason,
+            } => write!(
+                f,
+                "can't set `{path}` ({}) to {input:?}: {reason}",
+                expected.type_name()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SetValueError {}
+
+impl From<AccessError> for SetValueError {
+    fn from(error: AccessError) -> Self {
+        SetValueError::Access(error)
+    }
+}
+
+impl Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scalar::Unsigned(value) => write!(f, "{value}"),
+            Scalar::Signed(value) => write!(f, "{value}"),
+            Scalar::Float(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+impl Display for DynValue {
+    /// Scalars are written as is, vectors as `(x, y, z)` and matrices as a list of columns `[(..), (..)]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Print f32 components with f32 precision so 0.1 doesn't show as 0.10000000149011612. f16 and normalized
+        // components are exact in f32 too.
+        let component = |f: &mut fmt::Formatter<'_>, component: &Scalar| match component {
+            Scalar::Float(value) if self.ty.components().is_some_and(|(size, _)| size <= 4) => {
+                write!(f, "{}", *value as f32)
+            }
+            component => write!(f, "{component}"),
+        };
+        let vector = |f: &mut fmt::Formatter<'_>, components: &[Scalar]| {
+            write!(f, "(")?;
+            for (i, c) in components.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                component(f, c)?;
+            }
+            write!(f, ")")
+        };
+        match self.ty.shape() {
+            TypeShape::Matrix { rows, .. } => {
+                write!(f, "[")?;
+                for (i, column) in self.components.chunks(rows).enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    vector(f, column)?;
+                }
+                write!(f, "]")
+            }
+            TypeShape::Vector { .. } | TypeShape::Packed { .. } => vector(f, &self.components),
+            _ => match self.components.first() {
+                Some(c) => component(f, c),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+impl DynStruct {
+    /// Decoded value of the leaf field at `path`. None if the path doesn't exist or isn't a leaf.
+    pub fn value(&self, path: &[&str]) -> Option<DynValue> {
+        let field = self.layout.get_path(path)?;
+        self.field_value(field)
+    }
+
+    /// Parses `s` as the type of the field at `path` (see `DynValue::parse`), returning the field's offset and the
+    /// value.
+    fn parse_field(&self, path: &[&str], s: &str) -> Result<(usize, DynValue), SetValueError> {
+        let field = self.layout.try_get_path(path)?;
+        let error = |reason: String| SetValueError::Parse {
+            path: path.join("."),
+            expected: field.ty.clone(),
+            input: s.to_string(),
+            reason,
+        };
+        match DynValue::parse(&field.ty, s) {
+            Some(Ok(value)) => Ok((field.offset as usize, value)),
+            Some(Err(reason)) => Err(error(reason)),
+            None => Err(error("only fields with a value can be set".to_string())),
+        }
+    }
+
+    /// Sets the field at `path` from a string like `7`, `1, 2, 3` or `[(1, 0), (0, 1)]`, see `DynValue::parse`.
+    /// Nothing is written on error.
+    pub fn set_from_str(&mut self, path: &[&str], s: &str) -> Result<(), SetValueError> {
+        let (offset, value) = self.parse_field(path, s)?;
+        value.write(&mut self.data[offset..offset + value.ty.size_of()]);
+        Ok(())
+    }
+
+    /// The field at `path` formatted like `DynValue`'s `Display`. Structs are written as `{ a: 1, b: (0, 1) }` and
+    /// arrays as `[1, 2]`. None if the path doesn't exist.
+    pub fn get_as_string(&self, path: &[&str]) -> Option<String> {
+        fn write(dyn_struct: &DynStruct, ty: &BaseType, offset: usize, out: &mut String) {
+            match ty {
+                BaseType::Struct(layout) => {
+                    out.push_str("{ ");
+                    for (i, (name, field)) in layout.fields.iter().enumerate() {
+                        if i > 0 {
+                            out.push_str(", ");
+                        }
+                        out.push_str(name);
+                        out.push_str(": ");
+                        write(dyn_struct, &field.ty, field.offset as usize, out);
+                    }
+                    out.push_str(" }");
+                }
+                BaseType::Array(layout) => {
+                    out.push('[');
+                    for (i, (_, field)) in layout.fields.iter().enumerate() {
+                        if i > 0 {
+                            out.push_str(", ");
+                        }
+                        write(dyn_struct, &field.ty, field.offset as usize, out);
+                    }
+                    out.push(']');
+                }
+                ty => {
+                    let bytes = &dyn_struct.data[offset..offset + ty.size_of()];
+                    if let Some(value) = DynValue::read(ty, bytes) {
+                        out.push_str(&value.to_string());
+                    }
+                }
+            }
+        }
+        let field = self.layout.get_path(path)?;
+        let mut out = String::new();
+        write(self, &field.ty, field.offset as usize, &mut out);
+        Some(out)
+    }
+
+    /// Decoded value of `field`, which must be a field of this struct's layout. None if it isn't a leaf.
+    pub fn field_value(&self, field: &DynField) -> Option<DynValue> {
+        let offset = field.offset as usize;
+        DynValue::read(&field.ty, &self.data[offset..offset + field.ty.size_of()])
+    }
+}
+
+impl TrackedDynStruct {
+    /// `DynStruct::set_from_str` that only marks the stride blocks whose bytes changed. Returns whether anything
+    /// changed.
+    pub fn set_from_str(&mut self, path: &[&str], s: &str) -> Result<bool, SetValueError> {
+        let (offset, value) = self.dyn_struct.parse_field(path, s)?;
+        let range = offset..offset + value.ty.size_of();
+        let old = self.dyn_struct.data[range.clone()].to_vec();
+        value.write(&mut self.dyn_struct.data[range]);
+        Ok(self.mark_differing(offset, &old))
+    }
+
+    #[inline(always)]
+    pub fn get_as_string(&self, path: &[&str]) -> Option<String> {
+        self.dyn_struct.get_as_string(path)
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use bytemuck::{pod_read_unaligned, Pod, Zeroable};
+
+use crate::{dyn_layout::DynLayout, dyn_struct::DynStruct, dyn_value::DynValue};
+
+/// Borrowed struct over bytes that aren't necessarily aligned, like a slice of a file or a network packet.
+/// `get` borrows in place when the field happens to be aligned, `read_unaligned` always works.
+#[derive(Clone, Copy, Debug)]
+pub struct DynView<'a> {
+    pub data: &'a [u8],
+    pub layout: &'a Arc<DynLayout>,
+}
+
+impl<'a> DynView<'a> {
+    /// Panics if `data` isn't the size of the layout.
+    pub fn new(data: &'a [u8], layout: &'a Arc<DynLayout>) -> Self {
+        if layout.size != data.len() {
+            panic!(
+                "DynView layout does not match data length ({} != {}). Layout: {:?}",
+                layout.size,
+                data.len(),
+                layout.name
+            )
+        }
+        DynView { data, layout }
+    }
+
+    /// Whether the data is aligned for every field, in which case `get` never returns None for an existing field.
+    pub fn is_aligned(&self) -> bool {
+        (self.data.as_ptr() as usize).is_multiple_of(self.layout.align())
+    }
+
+    /// Borrows the field at `path`. None if the path doesn't exist or the field isn't aligned for `T`.
+    pub fn get<T: Pod + Zeroable>(&self, path: &[&str]) -> Option<&'a T> {
+        let field = self.layout.get_path(path)?;
+        debug_assert_eq!(size_of::<T>(), field.ty.size_of());
+        let offset = field.offset as usize;
+        bytemuck::try_from_bytes(&self.data[offset..offset + size_of::<T>()]).ok()
+    }
+
+    /// Copies the field at `path` out regardless of alignment. None if the path doesn't exist.
+    pub fn read_unaligned<T: Pod + Zeroable>(&self, path: &[&str]) -> Option<T> {
+        let field = self.layout.get_path(path)?;
+        debug_assert_eq!(size_of::<T>(), field.ty.size_of());
+        Some(self.read_unaligned_raw(field.offset as usize))
+    }
+
+    pub fn read_unaligned_raw<T: Pod + Zeroable>(&self, offset: usize) -> T {
+        pod_read_unaligned(&self.data[offset..offset + size_of::<T>()])
+    }
+
+    /// Decoded value of the leaf field at `path`, see `DynStruct::value`
+    pub fn value(&self, path: &[&str]) -> Option<DynValue> {
+        let field = self.layout.get_path(path)?;
+        let offset = field.offset as usize;
+        DynValue::read(&field.ty, &self.data[offset..offset + field.ty.size_of()])
+    }
+
+    /// Copies the data into aligned storage
+    pub fn to_dyn_struct(&self) -> DynStruct {
+        DynStruct::from_bytes(self.data, self.layout.clone())
+    }
+}
+
+impl DynStruct {
+    pub fn view(&self) -> DynView<'_> {
+        DynView::new(&self.data, &self.layout)
+    }
+}
@@ -4,12 +4,35 @@ pub mod bevy_reflect_for_tracked_dyn;
 pub mod spirv;
 use base_type::BaseType;
 use dyn_layout::DynLayout;
+pub mod access;
+pub mod aligned_bytes;
 pub mod base_type;
+pub mod blend;
+pub mod byte_map;
+pub mod change_stats;
+pub mod dyn_file;
+pub mod dyn_history;
 pub mod dyn_layout;
+pub mod dyn_layout_builder;
+pub mod dyn_patch;
 pub mod dyn_struct;
+pub mod dyn_value;
+pub mod dyn_view;
+pub mod lint;
+pub mod packed;
+pub mod packing;
+pub mod reorder;
+pub mod scatter_shader;
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod serde_for_dyn;
 pub mod tracked_dyn_struct;
 
 pub mod update_bitmask;
+pub mod upload_batch;
+pub mod upload_plan;
+pub mod validate;
+pub mod value_diff;
 
 /// Usage
 /// T: data type of slice
@@ -44,20 +67,46 @@ macro_rules! retrieve_changes {
             let mut total = 0;
             while chunk != 0 {
                 // Start from LSB and chip away at chunk while making copies the size of contiguous ones
+                let zeros_count = chunk.trailing_zeros();
+                chunk >>= zeros_count;
+                total += zeros_count as usize;
                 let count = chunk.trailing_ones() as usize;
                 let start = chunk_index + total;
                 let end = (chunk_index + total + count).min(data_length);
                 $extend_fn(&src_data[start..end], start as u32, end as u32);
                 total += count;
                 chunk = chunk.checked_shr(count as u32).unwrap_or(0);
-                let zeros_count = chunk.trailing_zeros();
-                chunk = chunk.checked_shr(zeros_count).unwrap_or(0);
-                total += zeros_count as usize;
             }
         }
     }};
 }
 
+/// Like `retrieve_changes!` but merges runs across chunks, see `TrackedDynStruct::retrieve_changes_coalesced`
+///
+/// retrieve_changes_coalesced!(u32, dyn_struct, 1, 4, |data_slice, start, end| {
+///     update_data.extend_from_slice(data_slice);
+///     indices.extend((dst_offset + start)..(dst_offset + end));
+/// });
+#[macro_export]
+macro_rules! retrieve_changes_coalesced {
+    ($T:ty, $dyn_struct:expr, $max_gap:expr, $min_run:expr, $extend_fn:expr) => {{
+        if !$dyn_struct.update_bitmask.any_set() || $dyn_struct.dyn_struct.data.is_empty() {
+            return;
+        }
+
+        let src_data: &[$T] = bytemuck::cast_slice(&$dyn_struct.dyn_struct.data);
+        let data_length = src_data.len();
+
+        for range in $dyn_struct
+            .update_bitmask
+            .coalesced_ranges($max_gap, $min_run)
+        {
+            let end = range.end.min(data_length);
+            $extend_fn(&src_data[range.start..end], range.start as u32, end as u32);
+        }
+    }};
+}
+
 #[macro_export]
 macro_rules! retrieve_changes_and_reset {
     ($T:ty, $dyn_struct:expr, $extend_fn:expr) => {{
@@ -66,41 +115,5 @@ macro_rules! retrieve_changes_and_reset {
     }};
 }
 
-/*
-Example shader to update retained buffer
-
-struct Config
-{
-    uint count;
-    uint spare1;
-    uint spare2;
-    uint spare3;
-};
-
-struct DataCopy
-{
-    uint data[UPDATE_STRIDE];
-};
-
-#include "shader_util.hlsl"
-PUSH_CONSTANT(Config, conf)
-
-[[vk::binding(0, 0)]]
-StructuredBuffer<DataCopy> src_buffer : register(t0, space0);
-[[vk::binding(1, 0)]]
-StructuredBuffer<uint> update_indices_buffer : register(t0, space0);
-[[vk::binding(2, 0)]]
-RWStructuredBuffer<DataCopy> dst_buffer : register(u0, space0);
-
-[numthreads(64, 1, 1)]
-void main(uint3 dispatchThreadID: SV_DispatchThreadID, uint3 groupID: SV_GroupID, uint3 groupThreadID: SV_GroupThreadID)
-{
-    int update_index = dispatchThreadID.x;
-    if (update_index >= conf.count)
-    {
-        return;
-    }
-    uint destination_index = update_indices_buffer[update_index];
-    dst_buffer[destination_index] = src_buffer[update_index];
-}
-*/
+// The compute shader that applies the update_data and indices to the retained buffer is generated by
+// `scatter_shader::ScatterShader`, see `upload_batch::RetainedUploadBatch` for gathering them.
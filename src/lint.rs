@@ -0,0 +1,185 @@
+use std::fmt::{self, Display};
+
+use crate::{base_type::BaseType, dyn_layout::DynLayout, packing::TypeShape};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Display for LintSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintSeverity::Info => write!(f, "info"),
+            LintSeverity::Warning => write!(f, "warning"),
+            LintSeverity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A soft problem with a layout. Unlike `LayoutViolation` these don't break any packing rules.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutLint {
+    pub severity: LintSeverity,
+    /// Path to the field separated by `.`, or the name of the struct for lints about the whole struct.
+    pub path: String,
+    pub kind: LintKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LintKind {
+    /// Bytes of padding in the struct (including nested structs, arrays and trailing padding).
+    WastedPadding { bytes: usize, percent: f32 },
+    /// A 3 component vector that isn't followed by a 4 byte scalar packed into its last 4 bytes. In std140 & std430
+    /// the vector is aligned to 16 bytes so the rust and shader layouts will likely disagree.
+    Vec3Member,
+    /// The struct is larger than the push constant limit.
+    PushConstantTooLarge { size: usize, limit: usize },
+    /// The field crosses a 16 byte boundary. Not allowed in HLSL cbuffers and slower to load on some hardware.
+    StraddlesVec4Boundary,
+    /// 64 bit floats are slow or unsupported on many GPUs.
+    DoublePrecision,
+    /// `size` isn't a multiple of `update_stride`, the bytes at the end won't have change tracking.
+    UpdateStride { update_stride: usize, size: usize },
+}
+
+impl Display for LayoutLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: ", self.severity, self.path)?;
+        match &self.kind {
+            LintKind::WastedPadding { bytes, percent } => {
+                write!(f, "{bytes} bytes of padding ({percent:.1}% of the struct)")
+            }
+            LintKind::Vec3Member => write!(
+                f,
+                "3 component vector isn't followed by a 4 byte scalar, std140 & std430 align it to 16 bytes"
+            ),
+            LintKind::PushConstantTooLarge { size, limit } => write!(
+                f,
+                "{size} bytes is larger than the {limit} byte push constant limit"
+            ),
+            LintKind::StraddlesVec4Boundary => write!(f, "crosses a 16 byte boundary"),
+            LintKind::DoublePrecision => write!(f, "64 bit float is slow or unsupported on many GPUs"),
+            LintKind::UpdateStride { update_stride, size } => write!(
+                f,
+                "update stride {update_stride} doesn't divide the struct size {size}, trailing bytes won't be tracked"
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintOptions {
+    /// The struct is bound to shaders, enables `DoublePrecision`.
+    pub shader_bound: bool,
+    /// Set when the struct is used as a push constant block. 128 bytes is the minimum limit guaranteed by Vulkan.
+    pub push_constant_limit: Option<usize>,
+    /// The `update_stride` used with `TrackedDynStruct`.
+    pub update_stride: Option<usize>,
+    /// Only report `WastedPadding` when it is at least this percentage of the struct size.
+    pub padding_threshold_percent: f32,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        LintOptions {
+            shader_bound: true,
+            push_constant_limit: None,
+            update_stride: None,
+            padding_threshold_percent: 0.0,
+        }
+    }
+}
+
+impl DynLayout {
+    /// Flags soft problems with the layout. See `LintKind`. For hard packing rule errors use `validate`.
+    pub fn lint(&self, options: &LintOptions) -> Vec<LayoutLint> {
+        let mut lints = Vec::new();
+        let mut lint = |severity, path: &str, kind| {
+            lints.push(LayoutLint {
+                severity,
+                path: path.to_string(),
+                kind,
+            })
+        };
+        let start = self.fields.first().map(|(_, f)| f.offset).unwrap_or(0) as usize;
+
+        let bytes = self
+            .padding()
+            .iter()
+            .map(|range| range.len())
+            .sum::<usize>();
+        if bytes > 0 {
+            let percent = bytes as f32 / self.size as f32 * 100.0;
+            if percent >= options.padding_threshold_percent {
+                lint(
+                    LintSeverity::Info,
+                    &self.name,
+                    LintKind::WastedPadding { bytes, percent },
+                );
+            }
+        }
+
+        if let Some(limit) = options.push_constant_limit {
+            if self.size > limit {
+                let size = self.size;
+                lint(
+                    LintSeverity::Error,
+                    &self.name,
+                    LintKind::PushConstantTooLarge { size, limit },
+                );
+            }
+        }
+
+        if let Some(update_stride) = options.update_stride {
+            if update_stride == 0 || !self.size.is_multiple_of(update_stride) {
+                let size = self.size;
+                lint(
+                    LintSeverity::Warning,
+                    &self.name,
+                    LintKind::UpdateStride {
+                        update_stride,
+                        size,
+                    },
+                );
+            }
+        }
+
+        let leaves = self.leaves();
+        for (i, (path, field)) in leaves.iter().enumerate() {
+            let offset = field.offset as usize - start;
+            let size = field.ty.size_of();
+            match field.ty.shape() {
+                TypeShape::Vector {
+                    component_size: 4,
+                    len: 3,
+                } => {
+                    let packed = leaves.get(i + 1).is_some_and(|(_, next)| {
+                        next.offset as usize - start == offset + 12 && next.ty.size_of() == 4
+                    });
+                    if !packed || !offset.is_multiple_of(16) {
+                        lint(LintSeverity::Warning, path, LintKind::Vec3Member);
+                    }
+                }
+                TypeShape::Vector {
+                    component_size: 8, ..
+                }
+                | TypeShape::Matrix {
+                    component_size: 8, ..
+                } if options.shader_bound => {
+                    lint(LintSeverity::Warning, path, LintKind::DoublePrecision)
+                }
+                _ => (),
+            }
+            if field.ty == BaseType::F64 && options.shader_bound {
+                lint(LintSeverity::Warning, path, LintKind::DoublePrecision);
+            }
+            if size > 0 && size <= 16 && offset / 16 != (offset + size - 1) / 16 {
+                lint(LintSeverity::Warning, path, LintKind::StraddlesVec4Boundary);
+            }
+        }
+        lints
+    }
+}
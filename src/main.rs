@@ -139,31 +139,31 @@ fn main() {
     println!();
 
     timeit!["Modify native",
-    black_box(native_instances.iter_mut().for_each(|instance| instance.first_index = 0 ));
+    black_box(native_instances.iter_mut()).for_each(|instance| instance.first_index = 0 );
     ];
 
     timeit!["Modify TrackedDynStructs",
-    black_box(instances.iter_mut().for_each(|instance| *instance.get_mut::<u32>(&["first_index"]).unwrap() = 0 ));
+    black_box(instances.iter_mut()).for_each(|instance| *instance.get_mut::<u32>(&["first_index"]).unwrap() = 0 );
     ];
 
     timeit!["Modify TrackedDynStructs fast",
     let offset = instances[0].dyn_struct.layout.get_path(&["first_index"]).unwrap().offset as usize;
-    black_box(instances.iter_mut().for_each(|instance| *instance.get_mut_raw::<u32>(offset) = 0 ));
+    black_box(instances.iter_mut()).for_each(|instance| *instance.get_mut_raw::<u32>(offset) = 0 );
     ];
 
     #[cfg(feature = "bevy_reflect")]
     timeit!["Modify bevy reflect TrackedDynStructs",
-    black_box(instances.iter_mut().for_each(|instance| *instance.get_field_mut::<u32>("first_index").unwrap() = 0));
+    black_box(instances.iter_mut()).for_each(|instance| *instance.get_field_mut::<u32>("first_index").unwrap() = 0);
     ];
 
     #[cfg(feature = "bevy_reflect")]
     timeit!["Modify bevy reflect native",
-    black_box(native_instances.iter_mut().for_each(|instance| *instance.get_field_mut::<u32>("first_index").unwrap() = 0));
+    black_box(native_instances.iter_mut()).for_each(|instance| *instance.get_field_mut::<u32>("first_index").unwrap() = 0);
     ];
 
     #[cfg(feature = "bevy_reflect")]
     timeit!["Modify bevy reflect DynamicStruct",
-    black_box(bevy_dyn_struct.iter_mut().for_each(|instance| *instance.get_field_mut::<u32>("first_index").unwrap() = 0));
+    black_box(bevy_dyn_struct.iter_mut()).for_each(|instance| *instance.get_field_mut::<u32>("first_index").unwrap() = 0);
     ];
 }
 
@@ -210,6 +210,7 @@ impl std::fmt::Display for PrettyDuration {
     /// - If the duration is greater than or equal to 1 second, it is formatted in seconds (s).
     /// - If the duration is greater than or equal to 1 millisecond but less than 1 second, it is formatted in milliseconds (ms).
     /// - If the duration is less than 1 millisecond, it is formatted in microseconds (µs).
+    ///
     /// In the case of seconds & milliseconds, the duration is always printed with a precision of two decimal places.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let duration = self.0;
@@ -0,0 +1,240 @@
+#[cfg(feature = "bevy_reflect")]
+use bevy_reflect::Reflect;
+use bytemuck::{pod_read_unaligned, Pod, Zeroable};
+use glam::{Vec2, Vec3, Vec4};
+
+pub use half::f16;
+
+use crate::{
+    base_type::{get_base_type, IntoBaseType},
+    dyn_struct::DynStruct,
+    dyn_view::DynView,
+    tracked_dyn_struct::TrackedDynStruct,
+};
+
+/// Type stored in a smaller form than the f32 or glam vector it represents, like `f16` or `Unorm8x4`.
+pub trait Packed: Pod + IntoBaseType {
+    type Unpacked;
+
+    /// Encodes `value`. Normalized components are clamped to their range and rounded to the nearest step.
+    fn pack(value: Self::Unpacked) -> Self;
+    fn unpack(self) -> Self::Unpacked;
+}
+
+/// Two half precision floats, aligned to 4 bytes like a shader f16vec2
+#[repr(C, align(4))]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(Reflect),
+    reflect(opaque, Debug, PartialEq)
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct F16x2(pub [f16; 2]);
+
+/// Three half precision floats, 6 bytes. Shaders align a f16vec3 like a f16vec4 but may pack a scalar after it, so
+/// this is only aligned to 2 bytes. `DynLayout::validate` reports fields of this type that std140 or std430 would
+/// place elsewhere.
+#[repr(C)]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(Reflect),
+    reflect(opaque, Debug, PartialEq)
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct F16x3(pub [f16; 3]);
+
+/// Four half precision floats, aligned to 8 bytes like a shader f16vec4
+#[repr(C, align(8))]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(Reflect),
+    reflect(opaque, Debug, PartialEq)
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct F16x4(pub [f16; 4]);
+
+/// Four unsigned normalized bytes decoding to [0, 1], like an RGBA colour packed into a `u32`
+#[repr(C, align(4))]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(Reflect),
+    reflect(opaque, Debug, PartialEq)
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Pod, Zeroable)]
+pub struct Unorm8x4(pub [u8; 4]);
+
+/// Four signed normalized bytes decoding to [-1, 1]
+#[repr(C, align(4))]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(Reflect),
+    reflect(opaque, Debug, PartialEq)
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Pod, Zeroable)]
+pub struct Snorm8x4(pub [i8; 4]);
+
+/// Two unsigned normalized 16 bit components decoding to [0, 1]
+#[repr(C, align(4))]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(Reflect),
+    reflect(opaque, Debug, PartialEq)
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Pod, Zeroable)]
+pub struct Unorm16x2(pub [u16; 2]);
+
+/// Two signed normalized 16 bit components decoding to [-1, 1], like an octahedral normal
+#[repr(C, align(4))]
+#[cfg_attr(
+    feature = "bevy_reflect",
+    derive(Reflect),
+    reflect(opaque, Debug, PartialEq)
+)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Pod, Zeroable)]
+pub struct Snorm16x2(pub [i16; 2]);
+
+impl Packed for f16 {
+    type Unpacked = f32;
+
+    #[inline(always)]
+    fn pack(value: f32) -> Self {
+        f16::from_f32(value)
+    }
+
+    #[inline(always)]
+    fn unpack(self) -> f32 {
+        self.to_f32()
+    }
+}
+
+impl Packed for F16x2 {
+    type Unpacked = Vec2;
+
+    #[inline(always)]
+    fn pack(value: Vec2) -> Self {
+        F16x2(value.to_array().map(f16::from_f32))
+    }
+
+    #[inline(always)]
+    fn unpack(self) -> Vec2 {
+        Vec2::from_array(self.0.map(f16::to_f32))
+    }
+}
+
+impl Packed for F16x3 {
+    type Unpacked = Vec3;
+
+    #[inline(always)]
+    fn pack(value: Vec3) -> Self {
+        F16x3(value.to_array().map(f16::from_f32))
+    }
+
+    #[inline(always)]
+    fn unpack(self) -> Vec3 {
+        Vec3::from_array(self.0.map(f16::to_f32))
+    }
+}
+
+impl Packed for F16x4 {
+    type Unpacked = Vec4;
+
+    #[inline(always)]
+    fn pack(value: Vec4) -> Self {
+        F16x4(value.to_array().map(f16::from_f32))
+    }
+
+    #[inline(always)]
+    fn unpack(self) -> Vec4 {
+        Vec4::from_array(self.0.map(f16::to_f32))
+    }
+}
+
+macro_rules! impl_normalized {
+    ($($name:ident($t:ty) => $vec:ty, $min:literal),* $(,)?) => {
+        $(
+            impl Packed for $name {
+                type Unpacked = $vec;
+
+                #[inline(always)]
+                fn pack(value: $vec) -> Self {
+                    let max = <$t>::MAX as f32;
+                    $name(value.to_array().map(|c| (c.clamp($min, 1.0) * max).round() as $t))
+                }
+
+                #[inline(always)]
+                fn unpack(self) -> $vec {
+                    // Signed formats have one more negative step than positive, both -MAX and MIN decode to -1
+                    let max = <$t>::MAX as f32;
+                    <$vec>::from_array(self.0.map(|c| (c as f32 / max).max(-1.0)))
+                }
+            }
+
+            impl $name {
+                /// From a `u32` with the first component in the lowest bits, like GLSL's `packUnorm4x8`
+                #[inline(always)]
+                pub fn from_bits(bits: u32) -> Self {
+                    bytemuck::cast(bits.to_le_bytes())
+                }
+
+                #[inline(always)]
+                pub fn to_bits(self) -> u32 {
+                    u32::from_le_bytes(bytemuck::cast(self))
+                }
+            }
+        )*
+    };
+}
+
+impl_normalized!(
+    Unorm8x4(u8) => Vec4, 0.0,
+    Snorm8x4(i8) => Vec4, -1.0,
+    Unorm16x2(u16) => Vec2, 0.0,
+    Snorm16x2(i16) => Vec2, -1.0,
+);
+
+impl DynStruct {
+    /// Decodes the field at `path`. None if the path doesn't exist or the field isn't a `P`.
+    pub fn get_unpacked<P: Packed>(&self, path: &[&str]) -> Option<P::Unpacked> {
+        let field = self.layout.get_path(path)?;
+        (field.ty == get_base_type::<P>())
+            .then(|| self.get_raw::<P>(field.offset as usize).unpack())
+    }
+
+    /// Encodes `value` into the field at `path`. None if the path doesn't exist or the field isn't a `P`.
+    pub fn set_unpacked<P: Packed>(&mut self, path: &[&str], value: P::Unpacked) -> Option<()> {
+        let field = self.layout.get_path(path)?;
+        if field.ty != get_base_type::<P>() {
+            return None;
+        }
+        *self.get_mut_raw::<P>(field.offset as usize) = P::pack(value);
+        Some(())
+    }
+}
+
+impl TrackedDynStruct {
+    #[inline(always)]
+    pub fn get_unpacked<P: Packed>(&self, path: &[&str]) -> Option<P::Unpacked> {
+        self.dyn_struct.get_unpacked::<P>(path)
+    }
+
+    /// `DynStruct::set_unpacked` that only marks the field as changed if the encoded value differs. Returns whether
+    /// it changed.
+    pub fn set_unpacked<P: Packed>(&mut self, path: &[&str], value: P::Unpacked) -> Option<bool> {
+        let field = self.dyn_struct.layout.get_path(path)?;
+        if field.ty != get_base_type::<P>() {
+            return None;
+        }
+        let offset = field.offset as usize;
+        Some(self.set_raw(offset, P::pack(value)))
+    }
+}
+
+impl DynView<'_> {
+    /// `DynStruct::get_unpacked` that works with unaligned data
+    pub fn read_unpacked<P: Packed>(&self, path: &[&str]) -> Option<P::Unpacked> {
+        let field = self.layout.get_path(path)?;
+        let offset = field.offset as usize;
+        (field.ty == get_base_type::<P>())
+            .then(|| pod_read_unaligned::<P>(&self.data[offset..offset + size_of::<P>()]).unpack())
+    }
+}
@@ -0,0 +1,240 @@
+use std::sync::Arc;
+
+use crate::{base_type::BaseType, dyn_layout::DynLayout};
+
+/// Rules used to decide where fields are placed in a struct.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PackingRules {
+    /// Rust `#[repr(C)]`. Fields are aligned to the alignment of the rust type. (Matches the `DynLayout` derive)
+    #[default]
+    ReprC,
+    /// GLSL std140, used for uniform buffers. Structs, arrays and matrix columns are aligned to 16 bytes.
+    Std140,
+    /// GLSL std430, used for storage buffers. Like std140 without rounding structs, arrays and matrix columns up to 16.
+    Std430,
+    /// Vulkan scalar block layout (VK_EXT_scalar_block_layout). Everything is aligned to its scalar component.
+    Scalar,
+    /// HLSL constant buffers. Fields can't straddle a 16 byte register, structs and matrices start on a new register.
+    HlslCbuffer,
+}
+
+/// What a `BaseType` is made of in terms of scalar components. Used when applying GPU packing rules.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TypeShape<'a> {
+    None,
+    Scalar {
+        size: usize,
+    },
+    Vector {
+        component_size: usize,
+        len: usize,
+    },
+    /// Normalized components packed into one 32 bit word, aligned like a `u32` rather than a vector.
+    Packed {
+        component_size: usize,
+        len: usize,
+    },
+    /// Column major, `rows` is the length of each column vector.
+    Matrix {
+        component_size: usize,
+        columns: usize,
+        rows: usize,
+    },
+    Struct(&'a Arc<DynLayout>),
+    Array(&'a Arc<DynLayout>),
+}
+
+impl BaseType {
+    pub fn shape(&self) -> TypeShape<'_> {
+        let vector = |component_size, len| TypeShape::Vector {
+            component_size,
+            len,
+        };
+        let matrix = |component_size, columns, rows| TypeShape::Matrix {
+            component_size,
+            columns,
+            rows,
+        };
+        match self {
+            BaseType::None => TypeShape::None,
+            BaseType::U8 | BaseType::I8 => TypeShape::Scalar { size: 1 },
+            BaseType::U16 | BaseType::I16 => TypeShape::Scalar { size: 2 },
+            BaseType::U32 | BaseType::I32 | BaseType::F32 => TypeShape::Scalar { size: 4 },
+            BaseType::U64 | BaseType::I64 | BaseType::F64 => TypeShape::Scalar { size: 8 },
+            BaseType::U128 | BaseType::I128 => TypeShape::Scalar { size: 16 },
+            BaseType::UVec2 | BaseType::IVec2 | BaseType::Vec2 => vector(4, 2),
+            BaseType::UVec3 | BaseType::IVec3 | BaseType::Vec3 => vector(4, 3),
+            BaseType::UVec4 | BaseType::IVec4 | BaseType::Vec4 | BaseType::Quat => vector(4, 4),
+            BaseType::DVec2 => vector(8, 2),
+            BaseType::DVec3 => vector(8, 3),
+            BaseType::DVec4 => vector(8, 4),
+            BaseType::Mat2 => matrix(4, 2, 2),
+            BaseType::Mat3 => matrix(4, 3, 3),
+            BaseType::Mat4 => matrix(4, 4, 4),
+            BaseType::DMat2 => matrix(8, 2, 2),
+            BaseType::DMat3 => matrix(8, 3, 3),
+            BaseType::DMat4 => matrix(8, 4, 4),
+            // Affines are stored as a matrix followed by the translation, so an extra column.
+            BaseType::DAffine2 => matrix(8, 3, 2),
+            BaseType::DAffine3 => matrix(8, 4, 3),
+            BaseType::F16 => TypeShape::Scalar { size: 2 },
+            BaseType::F16x2 => vector(2, 2),
+            BaseType::F16x3 => vector(2, 3),
+            BaseType::F16x4 => vector(2, 4),
+            BaseType::Unorm8x4 | BaseType::Snorm8x4 => TypeShape::Packed {
+                component_size: 1,
+                len: 4,
+            },
+            BaseType::Unorm16x2 | BaseType::Snorm16x2 => TypeShape::Packed {
+                component_size: 2,
+                len: 2,
+            },
+            BaseType::Struct(layout) => TypeShape::Struct(layout),
+            BaseType::Array(layout) => TypeShape::Array(layout),
+        }
+    }
+}
+
+#[inline(always)]
+pub fn round_up(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+
+impl PackingRules {
+    /// Base alignment of a vector with `len` components.
+    fn vector_align(&self, component_size: usize, len: usize) -> usize {
+        match self {
+            PackingRules::Std140 | PackingRules::Std430 => match len {
+                2 => component_size * 2,
+                _ => component_size * 4,
+            },
+            PackingRules::ReprC | PackingRules::Scalar | PackingRules::HlslCbuffer => {
+                component_size
+            }
+        }
+    }
+
+    /// Distance in bytes between the columns of a matrix.
+    pub fn matrix_column_stride(&self, component_size: usize, rows: usize) -> usize {
+        match self {
+            PackingRules::ReprC | PackingRules::Scalar => component_size * rows,
+            PackingRules::Std430 => self.vector_align(component_size, rows),
+            PackingRules::Std140 => round_up(self.vector_align(component_size, rows), 16),
+            PackingRules::HlslCbuffer => round_up(component_size * rows, 16),
+        }
+    }
+
+    /// Distance in bytes between array elements of type `element`.
+    pub fn array_stride(&self, element: &BaseType) -> usize {
+        let size = self.size_of(element);
+        match self {
+            PackingRules::Std140 => round_up(size, round_up(self.align_of(element), 16)),
+            PackingRules::HlslCbuffer => round_up(size, 16),
+            _ => round_up(size, self.align_of(element)),
+        }
+    }
+
+    /// Required alignment of `ty` under these rules.
+    pub fn align_of(&self, ty: &BaseType) -> usize {
+        if *self == PackingRules::ReprC {
+            return ty.align_of();
+        }
+        match ty.shape() {
+            TypeShape::None => 1,
+            TypeShape::Scalar { size } => size,
+            TypeShape::Vector {
+                component_size,
+                len,
+            } => self.vector_align(component_size, len),
+            TypeShape::Packed {
+                component_size,
+                len,
+            } => component_size * len,
+            TypeShape::Matrix {
+                component_size,
+                rows,
+                ..
+            } => match self {
+                PackingRules::Std140 | PackingRules::HlslCbuffer => {
+                    round_up(self.vector_align(component_size, rows), 16)
+                }
+                _ => self.vector_align(component_size, rows),
+            },
+            TypeShape::Struct(layout) => {
+                let align = layout
+                    .fields
+                    .iter()
+                    .map(|(_, field)| self.align_of(&field.ty))
+                    .max()
+                    .unwrap_or(1);
+                match self {
+                    PackingRules::Std140 | PackingRules::HlslCbuffer => round_up(align, 16),
+                    _ => align,
+                }
+            }
+            TypeShape::Array(layout) => {
+                let align = layout
+                    .fields
+                    .first()
+                    .map(|(_, element)| self.align_of(&element.ty))
+                    .unwrap_or(1);
+                match self {
+                    PackingRules::Std140 | PackingRules::HlslCbuffer => round_up(align, 16),
+                    _ => align,
+                }
+            }
+        }
+    }
+
+    /// Bytes `ty` occupies under these rules, including any padding inside of it (like between matrix columns or at
+    /// the end of a struct) but not the padding before the next field.
+    pub fn size_of(&self, ty: &BaseType) -> usize {
+        match ty.shape() {
+            TypeShape::Matrix {
+                component_size,
+                columns,
+                rows,
+            } => {
+                let stride = self.matrix_column_stride(component_size, rows);
+                match self {
+                    // The last column doesn't take up the whole register so following fields can pack into it.
+                    PackingRules::HlslCbuffer => stride * (columns - 1) + component_size * rows,
+                    _ => stride * columns,
+                }
+            }
+            TypeShape::Struct(layout) => match self {
+                PackingRules::HlslCbuffer => layout.size,
+                _ => round_up(layout.size, self.align_of(ty)),
+            },
+            TypeShape::Array(layout) => match (self, layout.fields.first()) {
+                // Like matrices, following fields can pack into the last element's register.
+                (PackingRules::HlslCbuffer, Some((_, element))) => {
+                    layout.array_stride() * (layout.fields.len() - 1) + self.size_of(&element.ty)
+                }
+                _ => layout.size,
+            },
+            _ => ty.size_of(),
+        }
+    }
+
+    /// Whether the memory layout of `ty` under these rules is the same as its rust memory layout.
+    /// For example std430 puts `Mat3` columns 16 bytes apart, but glam's `Mat3` is tightly packed.
+    /// Array elements are checked too, fields inside of structs are not.
+    pub fn is_representable(&self, ty: &BaseType) -> bool {
+        match ty.shape() {
+            TypeShape::Matrix {
+                component_size,
+                rows,
+                ..
+            } => self.matrix_column_stride(component_size, rows) == component_size * rows,
+            TypeShape::Array(layout) => match layout.fields.first() {
+                Some((_, element)) => {
+                    layout.array_stride() == self.array_stride(&element.ty)
+                        && self.is_representable(&element.ty)
+                }
+                None => true,
+            },
+            _ => true,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::{cmp::Reverse, sync::Arc};
+
+use crate::{
+    dyn_layout::DynLayout, dyn_layout_builder::DynLayoutBuilder, dyn_struct::DynStruct,
+    packing::PackingRules,
+};
+
+/// Result of `DynLayout::optimize_field_order`
+#[derive(Clone, Debug)]
+pub struct ReorderedLayout {
+    pub layout: Arc<DynLayout>,
+    /// `remap[new_index]` is the index of the same field in the original layout.
+    pub remap: Vec<usize>,
+    /// Size of the original field order when placed with the same packing rules.
+    pub original_size: usize,
+}
+
+impl ReorderedLayout {
+    pub fn bytes_saved(&self) -> usize {
+        self.original_size - self.layout.size
+    }
+
+    /// Copies the field values of `original` (which must use the layout this was optimized from) into a new
+    /// DynStruct using the optimized layout.
+    pub fn remap_data(&self, original: &DynStruct) -> DynStruct {
+        let mut data = vec![0; self.layout.size];
+        for ((_, field), old_index) in self.layout.fields.iter().zip(&self.remap) {
+            let (_, old_field) = &original.layout.fields[*old_index];
+            let size = field.ty.size_of();
+            let src = old_field.offset as usize;
+            let dst = field.offset as usize;
+            data[dst..dst + size].copy_from_slice(&original.data[src..src + size]);
+        }
+        DynStruct::from_bytes(data, self.layout.clone())
+    }
+}
+
+impl DynLayout {
+    /// Proposes an order for the top level fields that minimizes the size and padding of the struct when placed with
+    /// `rules`. Nested structs are moved as a whole, their fields are not reordered.
+    /// Never returns an order that is larger than the original one.
+    /// Panics if a field can't be represented with `rules`, see `DynLayoutBuilder::add_field`.
+    pub fn optimize_field_order(&self, rules: PackingRules) -> ReorderedLayout {
+        let original = (0..self.fields.len()).collect::<Vec<_>>();
+
+        // Largest alignment first is optimal when sizes are multiples of alignments (like repr(C))
+        let mut by_align = original.clone();
+        by_align.sort_by_key(|i| {
+            let ty = &self.fields[*i].1.ty;
+            (Reverse(rules.align_of(ty)), Reverse(rules.size_of(ty)))
+        });
+
+        // Greedily pick the field that needs the least padding next. Handles things like packing a scalar after a
+        // std430 vec3 that sorting by alignment misses.
+        let mut greedy = Vec::with_capacity(original.len());
+        let mut remaining = original.clone();
+        let mut builder = DynLayoutBuilder::new(&self.name, rules);
+        while !remaining.is_empty() {
+            let (pick, _) = remaining
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, i)| {
+                    let ty = &self.fields[**i].1.ty;
+                    let padding = builder.next_offset(ty) - builder.offset();
+                    (
+                        padding,
+                        Reverse(rules.align_of(ty)),
+                        Reverse(rules.size_of(ty)),
+                    )
+                })
+                .unwrap();
+            let i = remaining.remove(pick);
+            let (name, field) = &self.fields[i];
+            builder.add_field(name, field.ty.clone());
+            greedy.push(i);
+        }
+
+        let original_size = self.build_order(rules, &original).size();
+        let (remap, builder) = [original, by_align, greedy]
+            .into_iter()
+            .map(|order| {
+                let builder = self.build_order(rules, &order);
+                (order, builder)
+            })
+            // min_by_key returns the first minimum, so the original order wins ties.
+            .min_by_key(|(_, builder)| {
+                let padding = builder.padding().iter().map(|r| r.len()).sum::<usize>();
+                (builder.size(), padding)
+            })
+            .unwrap();
+
+        ReorderedLayout {
+            layout: builder.build(),
+            remap,
+            original_size,
+        }
+    }
+
+    fn build_order(&self, rules: PackingRules, order: &[usize]) -> DynLayoutBuilder {
+        let mut builder = DynLayoutBuilder::new(&self.name, rules);
+        for i in order {
+            let (name, field) = &self.fields[*i];
+            builder.add_field(name, field.ty.clone());
+        }
+        builder
+    }
+}
@@ -0,0 +1,176 @@
+use crate::upload_batch::RetainedUploadBatch;
+
+/// Generates the compute shader that scatters a `RetainedUploadBatch` into the retained buffer.
+///
+/// Bindings, all in group/space 0:
+/// - 0: `src`, read only storage buffer of `u32`, `RetainedUploadBatch::src`
+/// - 1: `update_indices`, read only storage buffer of `u32`, `RetainedUploadBatch::update_indices`
+/// - 2: `dst`, read write storage buffer of `u32`, the retained buffer
+/// - 3: `config`, uniform buffer with the update count, see `ScatterShader::config`
+///
+/// Dispatch `dispatch_count(batch.len())` workgroups in x.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScatterShader {
+    update_stride: usize,
+    workgroup_size: u32,
+}
+
+impl ScatterShader {
+    /// `update_stride` in bytes, panics if it isn't a power of 2 and a multiple of 4 or `workgroup_size` is 0.
+    pub fn new(update_stride: usize, workgroup_size: u32) -> Self {
+        assert!(
+            update_stride.is_power_of_two() && update_stride >= 4,
+            "Update stride {update_stride} must be a power of 2 and a multiple of 4"
+        );
+        assert!(workgroup_size > 0, "Workgroup size must be at least 1");
+        ScatterShader {
+            update_stride,
+            workgroup_size,
+        }
+    }
+
+    pub fn for_batch(batch: &RetainedUploadBatch, workgroup_size: u32) -> Self {
+        Self::new(batch.update_stride(), workgroup_size)
+    }
+
+    #[inline(always)]
+    pub fn update_stride(&self) -> usize {
+        self.update_stride
+    }
+
+    #[inline(always)]
+    pub fn workgroup_size(&self) -> u32 {
+        self.workgroup_size
+    }
+
+    /// Number of `u32` words copied per update, `UPDATE_STRIDE` in the shader
+    #[inline(always)]
+    pub fn stride_words(&self) -> u32 {
+        (self.update_stride / 4) as u32
+    }
+
+    /// Number of workgroups to dispatch for `updates` updates
+    #[inline(always)]
+    pub fn dispatch_count(&self, updates: usize) -> u32 {
+        (updates as u32).div_ceil(self.workgroup_size)
+    }
+
+    /// Contents of the `config` uniform buffer for `updates` updates, the rest is padding.
+    #[inline(always)]
+    pub fn config(&self, updates: usize) -> [u32; 4] {
+        [updates as u32, 0, 0, 0]
+    }
+
+    pub fn wgsl(&self) -> String {
+        format!(
+            r#"const UPDATE_STRIDE: u32 = {stride}u;
+
+struct Config {{
+    count: u32,
+    spare1: u32,
+    spare2: u32,
+    spare3: u32,
+}}
+
+@group(0) @binding(0)
+var<storage, read> src: array<u32>;
+@group(0) @binding(1)
+var<storage, read> update_indices: array<u32>;
+@group(0) @binding(2)
+var<storage, read_write> dst: array<u32>;
+@group(0) @binding(3)
+var<uniform> config: Config;
+
+@compute @workgroup_size({workgroup_size}, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {{
+    let update_index = global_id.x;
+    if (update_index >= config.count) {{
+        return;
+    }}
+    let src_start = update_index * UPDATE_STRIDE;
+    let dst_start = update_indices[update_index] * UPDATE_STRIDE;
+    for (var i = 0u; i < UPDATE_STRIDE; i++) {{
+        dst[dst_start + i] = src[src_start + i];
+    }}
+}}
+"#,
+            stride = self.stride_words(),
+            workgroup_size = self.workgroup_size,
+        )
+    }
+
+    pub fn hlsl(&self) -> String {
+        format!(
+            r#"#define UPDATE_STRIDE {stride}
+
+struct Config
+{{
+    uint count;
+    uint spare1;
+    uint spare2;
+    uint spare3;
+}};
+
+[[vk::binding(0, 0)]]
+StructuredBuffer<uint> src_buffer : register(t0, space0);
+[[vk::binding(1, 0)]]
+StructuredBuffer<uint> update_indices_buffer : register(t1, space0);
+[[vk::binding(2, 0)]]
+RWStructuredBuffer<uint> dst_buffer : register(u0, space0);
+[[vk::binding(3, 0)]]
+ConstantBuffer<Config> conf : register(b0, space0);
+
+[numthreads({workgroup_size}, 1, 1)]
+void main(uint3 dispatchThreadID : SV_DispatchThreadID)
+{{
+    uint update_index = dispatchThreadID.x;
+    if (update_index >= conf.count)
+    {{
+        return;
+    }}
+    uint src_start = update_index * UPDATE_STRIDE;
+    uint dst_start = update_indices_buffer[update_index] * UPDATE_STRIDE;
+    [unroll]
+    for (uint i = 0; i < UPDATE_STRIDE; i++)
+    {{
+        dst_buffer[dst_start + i] = src_buffer[src_start + i];
+    }}
+}}
+"#,
+            stride = self.stride_words(),
+            workgroup_size = self.workgroup_size,
+        )
+    }
+
+    /// Runs the shader on the CPU, invocation by invocation for `dispatch_count` workgroups of `workgroup_size` in
+    /// dispatch order. Like the shader, invocations past the update count in `config` return early. Reference for
+    /// testing the indices a batch produces without a GPU.
+    /// Panics if the update count is larger than `update_indices`, which would read out of bounds on the GPU.
+    pub fn scatter_cpu(
+        &self,
+        config: [u32; 4],
+        src: &[u32],
+        update_indices: &[u32],
+        dst: &mut [u32],
+    ) {
+        let count = config[0];
+        assert!(
+            count as usize <= update_indices.len(),
+            "Update count {count} is larger than the {} update indices",
+            update_indices.len()
+        );
+        let stride = self.stride_words() as usize;
+        for workgroup_id in 0..self.dispatch_count(count as usize) {
+            for local_id in 0..self.workgroup_size {
+                let update_index = workgroup_id * self.workgroup_size + local_id;
+                if update_index >= count {
+                    continue;
+                }
+                let src_start = update_index as usize * stride;
+                let dst_start = update_indices[update_index as usize] as usize * stride;
+                dst[dst_start..dst_start + stride]
+                    .copy_from_slice(&src[src_start..src_start + stride]);
+            }
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use fxhash::FxHashMap;
+
+use crate::{
+    base_type::{BaseType, LEAF_TYPES},
+    dyn_layout::DynLayout,
+    dyn_layout_builder::DynLayoutBuilder,
+    packing::PackingRules,
+};
+
+/// Layouts written in a small text format, for defining parameter blocks without Rust:
+///
+/// // Comments run to the end of the line
+/// packing std140
+///
+/// struct Light {
+///     position: Vec3,
+///     color: Vec4,
+/// }
+///
+/// struct Material {
+///     base_color: Vec4,
+///     lights: [Light; 4],
+///     flags: u32,
+/// }
+///
+/// Types are the `BaseType` names (`f32`, `u32`, `Vec3`, `Mat4`, ...), structs defined earlier in the file and
+/// arrays `[T; N]`. The optional `packing` line picks the rules (`repr_c`, `std140`, `std430`, `scalar` or
+/// `hlsl_cbuffer`) used to place the fields. `Display` writes the same format back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schema {
+    pub rules: PackingRules,
+    /// In definition order, so a struct only uses structs before it
+    pub structs: Vec<Arc<DynLayout>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaError {
+    /// 1 based
+    pub line: usize,
+    /// 1 based, in characters
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Why `Schema::from_layout` can't describe a layout
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FromLayoutError {
+    /// The packing rules place a field of struct `name` at a different offset, or give it a different size
+    Mismatch { name: String },
+    /// Different structs are both called `name`
+    DuplicateName { name: String },
+}
+
+impl Display for FromLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromLayoutError::Mismatch { name } => {
+                write!(f, "`{name}` doesn't follow the packing rules")
+            }
+            FromLayoutError::DuplicateName { name } => {
+                write!(f, "different structs are named `{name}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FromLayoutError {}
+
+fn rules_name(rules: PackingRules) -> &'static str {
+    match rules {
+        PackingRules::ReprC => "repr_c",
+        PackingRules::Std140 => "std140",
+        PackingRules::Std430 => "std430",
+        PackingRules::Scalar => "scalar",
+        PackingRules::HlslCbuffer => "hlsl_cbuffer",
+    }
+}
+
+impl Schema {
+    /// Parses a schema, placing fields with `rules` unless the schema has a `packing` line.
+    pub fn parse(src: &str, rules: PackingRules) -> Result<Schema, SchemaError> {
+        Parser {
+            tokens: tokenize(src)?,
+            pos: 0,
+            end: end_position(src),
+            rules,
+            structs: Vec::new(),
+            by_name: FxHashMap::default(),
+        }
+        .schema()
+    }
+
+    /// Schema of `layout` and every struct nested in it. Structs are written once per name.
+    /// Fails if two different structs share a name, or if `rules` would place any field or size a struct differently
+    /// than `layout` does, since parsing the schema wouldn't give back the same layout.
+    pub fn from_layout(layout: &DynLayout, rules: PackingRules) -> Result<Schema, FromLayoutError> {
+        fn collect(
+            layout: &DynLayout,
+            structs: &mut Vec<Arc<DynLayout>>,
+        ) -> Result<(), FromLayoutError> {
+            for (_, field) in &layout.fields {
+                let mut ty = &field.ty;
+                // The element type of (nested) arrays
+                while let BaseType::Array(array) = ty {
+                    match array.fields.first() {
+                        Some((_, element)) => ty = &element.ty,
+                        None => break,
+                    }
+                }
+                if let BaseType::Struct(nested) = ty {
+                    let nested = nested.rebased(0);
+                    match structs.iter().find(|s| s.name == nested.name) {
+                        Some(existing) if **existing != nested => {
+                            return Err(FromLayoutError::DuplicateName { name: nested.name });
+                        }
+                        Some(_) => {}
+                        None => {
+                            collect(&nested, structs)?;
+                            structs.push(Arc::new(nested));
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        let mut structs = Vec::new();
+        collect(layout, &mut structs)?;
+        if structs.iter().any(|s| s.name == layout.name) {
+            return Err(FromLayoutError::DuplicateName {
+                name: layout.name.clone(),
+            });
+        }
+        structs.push(Arc::new(layout.clone()));
+
+        for layout in &structs {
+            let mut builder = DynLayoutBuilder::new(&layout.name, rules);
+            for (name, field) in &layout.fields {
+                if !rules.is_representable(&field.ty) {
+                    return Err(FromLayoutError::Mismatch {
+                        name: layout.name.clone(),
+                    });
+                }
+                builder.add_field(name, field.ty.clone());
+            }
+            if *builder.build() != layout.rebased(0) {
+                return Err(FromLayoutError::Mismatch {
+                    name: layout.name.clone(),
+                });
+            }
+        }
+        Ok(Schema { rules, structs })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<DynLayout>> {
+        self.structs.iter().find(|layout| layout.name == name)
+    }
+
+    /// The last struct, which is usually the one the others are building blocks of
+    pub fn root(&self) -> Option<&Arc<DynLayout>> {
+        self.structs.last()
+    }
+}
+
+impl DynLayout {
+    /// The last struct of a schema, see `Schema`
+    pub fn parse_schema(src: &str, rules: PackingRules) -> Result<Arc<DynLayout>, SchemaError> {
+        let schema = Schema::parse(src, rules)?;
+        schema.root().cloned().ok_or_else(|| {
+            let (line, column) = end_position(src);
+            SchemaError {
+                line,
+                column,
+                message: "expected at least one struct".to_string(),
+            }
+        })
+    }
+}
+
+/// Name of `ty` as written in a schema
+fn schema_type_name(ty: &BaseType) -> String {
+    match ty {
+        BaseType::Array(array) => match array.fields.first() {
+            Some((_, element)) => format!(
+                "[{}; {}]",
+                schema_type_name(&element.ty),
+                array.fields.len()
+            ),
+            None => array.name.clone(),
+        },
+        ty => ty.type_name(),
+    }
+}
+
+impl Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "packing {}", rules_name(self.rules))?;
+        for layout in &self.structs {
+            writeln!(f)?;
+            writeln!(f, "struct {} {{", layout.name)?;
+            for (name, field) in &layout.fields {
+                writeln!(f, "    {name}: {},", schema_type_name(&field.ty))?;
+            }
+            writeln!(f, "}}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Number(usize),
+    Punct(char),
+}
+
+#[derive(Clone, Debug)]
+struct Spanned<'a> {
+    token: Token<'a>,
+    line: usize,
+    column: usize,
+}
+
+fn end_position(src: &str) -> (usize, usize) {
+    let line = src.lines().count().max(1);
+    let column = src.lines().last().map(|l| l.chars().count()).unwrap_or(0) + 1;
+    (line, column)
+}
+
+fn tokenize(src: &str) -> Result<Vec<Spanned<'_>>, SchemaError> {
+    let mut tokens = Vec::new();
+    for (line_index, line) in src.lines().enumerate() {
+        let mut chars = line.char_indices().enumerate().peekable();
+        while let Some((column, (start, c))) = chars.next() {
+            let spanned = |token| Spanned {
+                token,
+                line: line_index + 1,
+                column: column + 1,
+            };
+            if c.is_whitespace() {
+                continue;
+            }
+            if c == '/' && line[start..].starts_with("//") {
+                break;
+            }
+            if c.is_alphanumeric() || c == '_' {
+                let mut end = start + c.len_utf8();
+                while let Some((_, (i, c))) =
+                    chars.next_if(|(_, (_, c))| c.is_alphanumeric() || *c == '_')
+                {
+                    end = i + c.len_utf8();
+                }
+                let word = &line[start..end];
+                let token = if c.is_ascii_digit() {
+                    match word.parse() {
+                        Ok(number) => Token::Number(number),
+                        Err(_) => {
+                            return Err(SchemaError {
+                                line: line_index + 1,
+                                column: column + 1,
+                                message: format!("invalid number `{word}`"),
+                            })
+                        }
+                    }
+                } else {
+                    Token::Ident(word)
+                };
+                tokens.push(spanned(token));
+            } else if "{}[]:;,".contains(c) {
+                tokens.push(spanned(Token::Punct(c)));
+            } else {
+                return Err(SchemaError {
+                    line: line_index + 1,
+                    column: column + 1,
+                    message: format!("unexpected character `{c}`"),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Spanned<'a>>,
+    pos: usize,
+    /// Position errors at the end of the input are reported at
+    end: (usize, usize),
+    rules: PackingRules,
+    structs: Vec<Arc<DynLayout>>,
+    by_name: FxHashMap<String, Arc<DynLayout>>,
+}
+
+impl<'a> Parser<'a> {
+    fn error_at(&self, pos: usize, message: String) -> SchemaError {
+        let (line, column) = match self.tokens.get(pos) {
+            Some(token) => (token.line, token.column),
+            None => self.end,
+        };
+        SchemaError {
+            line,
+            column,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos).map(|t| &t.token)
+    }
+
+    fn describe(&self) -> String {
+        match self.peek() {
+            Some(Token::Ident(word)) => format!("`{word}`"),
+            Some(Token::Number(number)) => format!("`{number}`"),
+            Some(Token::Punct(c)) => format!("`{c}`"),
+            None => "end of input".to_string(),
+        }
+    }
+
+    fn expect_punct(&mut self, expected: char) -> Result<(), SchemaError> {
+        if self.peek() == Some(&Token::Punct(expected)) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error_at(
+                self.pos,
+                format!("expected `{expected}`, found {}", self.describe()),
+            ))
+        }
+    }
+
+    fn expect_ident(&mut self, what: &str) -> Result<&'a str, SchemaError> {
+        match self.peek() {
+            Some(Token::Ident(word)) => {
+                let word = *word;
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(self.error_at(
+                self.pos,
+                format!("expected {what}, found {}", self.describe()),
+            )),
+        }
+    }
+
+    fn schema(mut self) -> Result<Schema, SchemaError> {
+        if self.peek() == Some(&Token::Ident("packing")) {
+            self.pos += 1;
+            let start = self.pos;
+            let name = self.expect_ident("packing rules")?;
+            self.rules = [
+                PackingRules::ReprC,
+                PackingRules::Std140,
+                PackingRules::Std430,
+                PackingRules::Scalar,
+                PackingRules::HlslCbuffer,
+            ]
+            .into_iter()
+            .find(|rules| rules_name(*rules) == name)
+            .ok_or_else(|| self.error_at(start, format!("unknown packing rules `{name}`")))?;
+        }
+        while self.peek().is_some() {
+            let layout = self.struct_def()?;
+            self.by_name.insert(layout.name.clone(), layout.clone());
+            self.structs.push(layout);
+        }
+        Ok(Schema {
+            rules: self.rules,
+            structs: self.structs,
+        })
+    }
+
+    fn struct_def(&mut self) -> Result<Arc<DynLayout>, SchemaError> {
+        if self.peek() != Some(&Token::Ident("struct")) {
+            return Err(self.error_at(
+                self.pos,
+                format!("expected `struct`, found {}", self.describe()),
+            ));
+        }
+        self.pos += 1;
+        let name_pos = self.pos;
+        let name = self.expect_ident("a struct name")?;
+        if self.by_name.contains_key(name) || LEAF_TYPES.iter().any(|t| t.type_name() == name) {
+            return Err(self.error_at(name_pos, format!("`{name}` is already defined")));
+        }
+        self.expect_punct('{')?;
+        let mut builder = DynLayoutBuilder::new(name, self.rules);
+        let mut field_names = Vec::new();
+        while self.peek() != Some(&Token::Punct('}')) {
+            let field_pos = self.pos;
+            let field = self.expect_ident("a field name or `}`")?;
+            if field_names.contains(&field) {
+                return Err(self.error_at(field_pos, format!("duplicate field `{field}`")));
+            }
+            field_names.push(field);
+            self.expect_punct(':')?;
+            let ty_pos = self.pos;
+            let ty = self.ty()?;
+            if !self.rules.is_representable(&ty) {
+                return Err(self.error_at(
+                    ty_pos,
+                    format!(
+                        "{} can't be represented with {} packing",
+                        schema_type_name(&ty),
+                        rules_name(self.rules)
+                    ),
+                ));
+            }
+            builder.add_field(field, ty);
+            if self.peek() == Some(&Token::Punct(',')) {
+                self.pos += 1;
+            } else if self.peek() != Some(&Token::Punct('}')) {
+                return Err(self.error_at(
+                    self.pos,
+                    format!("expected `,` or `}}`, found {}", self.describe()),
+                ));
+            }
+        }
+        self.pos += 1;
+        Ok(builder.build())
+    }
+
+    fn ty(&mut self) -> Result<BaseType, SchemaError> {
+        if self.peek() == Some(&Token::Punct('[')) {
+            self.pos += 1;
+            let element = self.ty()?;
+            self.expect_punct(';')?;
+            let len_pos = self.pos;
+            let len = match self.peek() {
+                Some(Token::Number(len)) if *len > 0 => *len,
+                _ => {
+                    return Err(self.error_at(
+                        len_pos,
+                        format!("expected an array length, found {}", self.describe()),
+                    ))
+                }
+            };
+            self.pos += 1;
+            self.expect_punct(']')?;
+            let stride = self.rules.array_stride(&element);
+            return Ok(BaseType::Array(Arc::new(DynLayout::new_array(
+                element, len, stride, 0,
+            ))));
+        }
+        let pos = self.pos;
+        let name = self.expect_ident("a type")?;
+        if let Some(layout) = self.by_name.get(name) {
+            return Ok(BaseType::Struct(layout.clone()));
+        }
+        LEAF_TYPES
+            .iter()
+            .find(|ty| **ty != BaseType::None && ty.type_name() == name)
+            .cloned()
+            .ok_or_else(|| self.error_at(pos, format!("unknown type `{name}`")))
+    }
+}
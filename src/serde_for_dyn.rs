@@ -0,0 +1,595 @@
+use std::{fmt, sync::Arc};
+
+use fxhash::FxHashSet;
+use serde::{
+    de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    base_type::BaseType,
+    dyn_layout::DynLayout,
+    dyn_struct::{DynField, DynStruct},
+    dyn_value::{DynValue, Scalar, ScalarKind},
+    packing::TypeShape,
+};
+
+// Layouts serialize as a tree of
+// { name: "Instance", size: 48, fields: [{ name: "position", offset: 0, ty: "Vec3" }, { ..., ty: { Struct: {...} } }] }
+
+#[derive(Serialize)]
+struct FieldRef<'a> {
+    name: &'a str,
+    offset: u32,
+    ty: &'a BaseType,
+}
+
+#[derive(Serialize)]
+struct LayoutRef<'a> {
+    name: &'a str,
+    size: usize,
+    fields: Vec<FieldRef<'a>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "DynField", deny_unknown_fields)]
+struct FieldRepr {
+    name: String,
+    offset: u32,
+    ty: BaseType,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "DynLayout", deny_unknown_fields)]
+struct LayoutRepr {
+    name: String,
+    size: usize,
+    fields: Vec<FieldRepr>,
+}
+
+impl Serialize for DynLayout {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        LayoutRef {
+            name: &self.name,
+            size: self.size,
+            fields: self
+                .fields
+                .iter()
+                .map(|(name, field)| FieldRef {
+                    name,
+                    offset: field.offset,
+                    ty: &field.ty,
+                })
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DynLayout {
+    /// Errors if a field doesn't fit in the layout's size
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = LayoutRepr::deserialize(deserializer)?;
+        let start = repr.fields.first().map(|f| f.offset as usize).unwrap_or(0);
+        let mut fields = Vec::with_capacity(repr.fields.len());
+        for field in repr.fields {
+            let offset = field.offset as usize;
+            if offset < start || offset + field.ty.size_of() > start + repr.size {
+                return Err(de::Error::custom(format!(
+                    "field `{}` doesn't fit in layout `{}`",
+                    field.name, repr.name
+                )));
+            }
+            fields.push((
+                field.name,
+                DynField {
+                    offset: field.offset,
+                    ty: field.ty,
+                },
+            ));
+        }
+        Ok(DynLayout::new(&repr.name, repr.size, fields))
+    }
+}
+
+/// In human readable formats a map of field name to value. Vectors are arrays, matrices arrays of columns, nested
+/// structs maps and arrays sequences. Padding isn't written. Other formats get the raw bytes.
+/// Deserializing needs the layout, see `DynStructSeed`.
+impl Serialize for DynStruct {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            StructRef {
+                layout: &self.layout,
+                data: &self.data,
+            }
+            .serialize(serializer)
+        } else {
+            serializer.serialize_bytes(&self.data)
+        }
+    }
+}
+
+struct StructRef<'a> {
+    layout: &'a DynLayout,
+    /// Data of the top level struct, offsets are absolute
+    data: &'a [u8],
+}
+
+impl Serialize for StructRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.layout.fields.len()))?;
+        for (name, field) in &self.layout.fields {
+            map.serialize_entry(
+                name,
+                &FieldValueRef {
+                    field,
+                    data: self.data,
+                },
+            )?;
+        }
+        map.end()
+    }
+}
+
+struct FieldValueRef<'a> {
+    field: &'a DynField,
+    data: &'a [u8],
+}
+
+impl Serialize for FieldValueRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.field.ty {
+            BaseType::Struct(layout) => StructRef {
+                layout,
+                data: self.data,
+            }
+            .serialize(serializer),
+            BaseType::Array(layout) => {
+                let mut seq = serializer.serialize_seq(Some(layout.fields.len()))?;
+                for (_, field) in &layout.fields {
+                    seq.serialize_element(&FieldValueRef {
+                        field,
+                        data: self.data,
+                    })?;
+                }
+                seq.end()
+            }
+            ty => {
+                let offset = self.field.offset as usize;
+                match DynValue::read(ty, &self.data[offset..offset + ty.size_of()]) {
+                    Some(value) => ValueRef(&value).serialize(serializer),
+                    None => serializer.serialize_unit(),
+                }
+            }
+        }
+    }
+}
+
+struct ValueRef<'a>(&'a DynValue);
+
+impl Serialize for ValueRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = self.0;
+        let size = value.ty.components().map(|(size, _)| size).unwrap_or(0);
+        match value.ty.shape() {
+            TypeShape::Vector { .. } | TypeShape::Packed { .. } => {
+                ScalarsRef(&value.components, size).serialize(serializer)
+            }
+            TypeShape::Matrix { rows, .. } => {
+                let mut seq = serializer.serialize_seq(Some(value.components.len() / rows))?;
+                for column in value.components.chunks(rows) {
+                    seq.serialize_element(&ScalarsRef(column, size))?;
+                }
+                seq.end()
+            }
+            _ => ScalarRef(&value.components[0], size).serialize(serializer),
+        }
+    }
+}
+
+struct ScalarsRef<'a>(&'a [Scalar], usize);
+
+impl Serialize for ScalarsRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for scalar in self.0 {
+            seq.serialize_element(&ScalarRef(scalar, self.1))?;
+        }
+        seq.end()
+    }
+}
+
+/// Scalar and the size of its component, so f32 is written with f32 precision
+struct ScalarRef<'a>(&'a Scalar, usize);
+
+impl Serialize for ScalarRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self.0 {
+            Scalar::Unsigned(value) => match u64::try_from(value) {
+                Ok(value) => serializer.serialize_u64(value),
+                Err(_) => serializer.serialize_u128(value),
+            },
+            Scalar::Signed(value) => match i64::try_from(value) {
+                Ok(value) => serializer.serialize_i64(value),
+                Err(_) => serializer.serialize_i128(value),
+            },
+            // f16 and normalized components are exact in f32
+            Scalar::Float(value) if self.1 <= 4 => serializer.serialize_f32(value as f32),
+            Scalar::Float(value) => serializer.serialize_f64(value),
+        }
+    }
+}
+
+/// Deserializes a `DynStruct` of `layout`, checking that every field is present, has the right shape and that
+/// integers fit their type. Binary formats must hold exactly the layout's size in bytes.
+///
+/// let material = DynStructSeed(&layout).deserialize(&mut serde_json::Deserializer::from_str(json))?;
+#[derive(Clone, Copy)]
+pub struct DynStructSeed<'a>(pub &'a Arc<DynLayout>);
+
+impl<'de> DeserializeSeed<'de> for DynStructSeed<'_> {
+    type Value = DynStruct;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<DynStruct, D::Error> {
+        let layout = self.0;
+        if deserializer.is_human_readable() {
+            let mut data = vec![0; layout.size];
+            StructSeed {
+                layout,
+                data: &mut data,
+            }
+            .deserialize(deserializer)?;
+            Ok(DynStruct::from_bytes(data, layout.clone()))
+        } else {
+            let data = deserializer.deserialize_bytes(BytesVisitor)?;
+            if data.len() != layout.size {
+                return Err(de::Error::invalid_length(
+                    data.len(),
+                    &format!("{} bytes for {}", layout.size, layout.name).as_str(),
+                ));
+            }
+            Ok(DynStruct::from_bytes(data, layout.clone()))
+        }
+    }
+}
+
+impl DynStruct {
+    /// `DynStructSeed` as a function
+    pub fn deserialize_with_layout<'de, D: Deserializer<'de>>(
+        layout: &Arc<DynLayout>,
+        deserializer: D,
+    ) -> Result<DynStruct, D::Error> {
+        DynStructSeed(layout).deserialize(deserializer)
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+struct StructSeed<'a> {
+    layout: &'a DynLayout,
+    data: &'a mut [u8],
+}
+
+impl<'de> DeserializeSeed<'de> for StructSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for StructSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map of the fields of {}", self.layout.name)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut seen = FxHashSet::default();
+        while let Some(name) = map.next_key::<String>()? {
+            let Some(field) = self.layout.fields_hash.get(&name) else {
+                return Err(de::Error::custom(format!(
+                    "unknown field `{name}` in {}",
+                    self.layout.name
+                )));
+            };
+            if !seen.insert(name.clone()) {
+                return Err(de::Error::custom(format!("duplicate field `{name}`")));
+            }
+            map.next_value_seed(FieldSeed {
+                field,
+                data: &mut *self.data,
+            })?;
+        }
+        if let Some((name, _)) = self.layout.fields.iter().find(|(n, _)| !seen.contains(n)) {
+            return Err(de::Error::custom(format!(
+                "missing field `{name}` in {}",
+                self.layout.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+struct FieldSeed<'a> {
+    field: &'a DynField,
+    data: &'a mut [u8],
+}
+
+impl<'de> DeserializeSeed<'de> for FieldSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        let ty = &self.field.ty;
+        match ty {
+            BaseType::Struct(layout) => StructSeed {
+                layout,
+                data: self.data,
+            }
+            .deserialize(deserializer),
+            BaseType::Array(layout) => deserializer.deserialize_seq(ArraySeed {
+                layout,
+                data: self.data,
+            }),
+            _ => {
+                let (Some(kind), Some((size, _))) = (ty.scalar_kind(), ty.components()) else {
+                    return <()>::deserialize(deserializer);
+                };
+                let components = match ty.shape() {
+                    TypeShape::Vector { len, .. } | TypeShape::Packed { len, .. } => {
+                        deserializer.deserialize_seq(ScalarsSeed { kind, size, len })?
+                    }
+                    TypeShape::Matrix { columns, rows, .. } => {
+                        deserializer.deserialize_seq(ColumnsSeed {
+                            column: ScalarsSeed {
+                                kind,
+                                size,
+                                len: rows,
+                            },
+                            columns,
+                        })?
+                    }
+                    _ => vec![ScalarSeed { kind, size }.deserialize(deserializer)?],
+                };
+                let offset = self.field.offset as usize;
+                DynValue {
+                    ty: ty.clone(),
+                    components,
+                }
+                .write(&mut self.data[offset..offset + ty.size_of()]);
+                Ok(())
+            }
+        }
+    }
+}
+
+struct ArraySeed<'a> {
+    layout: &'a DynLayout,
+    data: &'a mut [u8],
+}
+
+impl<'de> Visitor<'de> for ArraySeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of {} elements", self.layout.fields.len())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let len = self.layout.fields.len();
+        for (i, (_, field)) in self.layout.fields.iter().enumerate() {
+            let element = FieldSeed {
+                field,
+                data: &mut *self.data,
+            };
+            if seq.next_element_seed(element)?.is_none() {
+                return Err(de::Error::invalid_length(i, &self));
+            }
+        }
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(len + 1, &self));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ColumnsSeed {
+    column: ScalarsSeed,
+    columns: usize,
+}
+
+impl<'de> Visitor<'de> for ColumnsSeed {
+    type Value = Vec<Scalar>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} columns of {} components",
+            self.columns, self.column.len
+        )
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<Scalar>, A::Error> {
+        let mut components = Vec::with_capacity(self.columns * self.column.len);
+        for i in 0..self.columns {
+            let column = seq
+                .next_element_seed(self.column)?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+            components.extend(column);
+        }
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(self.columns + 1, &self));
+        }
+        Ok(components)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ScalarsSeed {
+    kind: ScalarKind,
+    size: usize,
+    len: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for ScalarsSeed {
+    type Value = Vec<Scalar>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Vec<Scalar>, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ScalarsSeed {
+    type Value = Vec<Scalar>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of {} numbers", self.len)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<Scalar>, A::Error> {
+        let scalar = ScalarSeed {
+            kind: self.kind,
+            size: self.size,
+        };
+        let mut components = Vec::with_capacity(self.len);
+        for i in 0..self.len {
+            let component = seq
+                .next_element_seed(scalar)?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+            components.push(component);
+        }
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(self.len + 1, &self));
+        }
+        Ok(components)
+    }
+}
+
+/// One component of `size` bytes, integers are range checked
+#[derive(Clone, Copy)]
+struct ScalarSeed {
+    kind: ScalarKind,
+    size: usize,
+}
+
+impl ScalarSeed {
+    fn float<E: de::Error>(self, value: f64) -> Result<Scalar, E> {
+        let fits = match self.kind {
+            ScalarKind::Unorm => (0.0..=1.0).contains(&value),
+            ScalarKind::Snorm => (-1.0..=1.0).contains(&value),
+            _ => true,
+        };
+        if !fits {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Float(value),
+                &self,
+            ));
+        }
+        Ok(Scalar::Float(value))
+    }
+
+    fn int<E: de::Error>(self, value: i128) -> Result<Scalar, E> {
+        let bits = self.size as u32 * 8;
+        let fits = match self.kind {
+            ScalarKind::Float | ScalarKind::Unorm | ScalarKind::Snorm => {
+                return self.float(value as f64)
+            }
+            ScalarKind::Unsigned => value >= 0 && (bits >= 128 || value >> bits == 0),
+            ScalarKind::Signed => {
+                bits >= 128 || (value >> (bits - 1) == 0 || value >> (bits - 1) == -1)
+            }
+        };
+        if !fits {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Other(&value.to_string()),
+                &self,
+            ));
+        }
+        Ok(match self.kind {
+            ScalarKind::Unsigned => Scalar::Unsigned(value as u128),
+            _ => Scalar::Signed(value),
+        })
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for ScalarSeed {
+    type Value = Scalar;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Scalar, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ScalarSeed {
+    type Value = Scalar;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ScalarKind::Float => write!(f, "a number"),
+            ScalarKind::Unorm => write!(f, "a number between 0 and 1"),
+            ScalarKind::Snorm => write!(f, "a number between -1 and 1"),
+            ScalarKind::Unsigned => write!(f, "a {} bit unsigned integer", self.size * 8),
+            ScalarKind::Signed => write!(f, "a {} bit signed integer", self.size * 8),
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Scalar, E> {
+        self.int(v as i128)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Scalar, E> {
+        self.int(v as i128)
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Scalar, E> {
+        self.int(v)
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Scalar, E> {
+        match (self.kind, i128::try_from(v)) {
+            (ScalarKind::Unsigned, _) if self.size >= 16 => Ok(Scalar::Unsigned(v)),
+            (ScalarKind::Float | ScalarKind::Unorm | ScalarKind::Snorm, _) => self.float(v as f64),
+            (_, Ok(v)) => self.int(v),
+            (_, Err(_)) => Err(de::Error::invalid_value(
+                de::Unexpected::Other(&v.to_string()),
+                &self,
+            )),
+        }
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Scalar, E> {
+        match self.kind {
+            ScalarKind::Unsigned | ScalarKind::Signed => {
+                Err(de::Error::invalid_type(de::Unexpected::Float(v), &self))
+            }
+            _ => self.float(v),
+        }
+    }
+}
@@ -67,7 +67,7 @@ pub fn struct_to_layout(struct_type: spirq::ty::StructType, parent_offset: u32)
             .clone()
             .unwrap_or(format!("param_{}", fields.len()));
         let offset = member.offset.unwrap() as u32 + parent_offset;
-        let dyn_ty = spirq_ty_to_dyn(member, offset);
+        let dyn_ty = spirq_ty_to_dyn(&member.ty, offset);
         //dbg!(&name, (&u32_offset, &dyn_ty));
         fields.push((name, DynField { offset, ty: dyn_ty }));
     }
@@ -86,8 +86,8 @@ pub fn struct_to_layout(struct_type: spirq::ty::StructType, parent_offset: u32)
     ))
 }
 
-pub fn spirq_ty_to_dyn(member: &spirq::ty::StructMember, parent_offset: u32) -> BaseType {
-    let dyn_ty = match &member.ty {
+pub fn spirq_ty_to_dyn(ty: &spirq::ty::Type, parent_offset: u32) -> BaseType {
+    let dyn_ty = match ty {
         spirq::ty::Type::Scalar(scalar_type) => match *scalar_type {
             spirq::ty::ScalarType::Void => BaseType::None,
             spirq::ty::ScalarType::Boolean => unimplemented!(), // I think this bool is 32 bits
@@ -111,6 +111,7 @@ pub fn spirq_ty_to_dyn(member: &spirq::ty::StructMember, parent_offset: u32) ->
                 _ => unimplemented!(),
             },
             spirq::ty::ScalarType::Float { bits } => match bits {
+                16 => BaseType::F16,
                 32 => BaseType::F32,
                 64 => BaseType::F64,
                 _ => unimplemented!(),
@@ -139,16 +140,19 @@ pub fn spirq_ty_to_dyn(member: &spirq::ty::StructMember, parent_offset: u32) ->
             },
             spirq::ty::ScalarType::Float { bits } => match vector_type.nscalar {
                 2 => match bits {
+                    16 => BaseType::F16x2,
                     32 => BaseType::Vec2,
                     64 => BaseType::DVec2,
                     _ => unimplemented!(),
                 },
                 3 => match bits {
+                    16 => BaseType::F16x3,
                     32 => BaseType::Vec3,
                     64 => BaseType::DVec3,
                     _ => unimplemented!(),
                 },
                 4 => match bits {
+                    16 => BaseType::F16x4,
                     32 => BaseType::Vec4,
                     64 => BaseType::DVec4,
                     _ => unimplemented!(),
@@ -180,6 +184,21 @@ pub fn spirq_ty_to_dyn(member: &spirq::ty::StructMember, parent_offset: u32) ->
                     },
                     _ => unimplemented!(),
                 },
+                // There's no f16 matrix type, so the columns become an array of f16 vectors
+                16 => {
+                    let column = match matrix_type.vector_ty.nscalar {
+                        2 => BaseType::F16x2,
+                        3 => BaseType::F16x3,
+                        _ => BaseType::F16x4,
+                    };
+                    let stride = matrix_type.stride.unwrap_or(column.size_of());
+                    BaseType::Array(Arc::new(DynLayout::new_array(
+                        column,
+                        matrix_type.nvector as usize,
+                        stride,
+                        parent_offset,
+                    )))
+                }
                 _ => unimplemented!(),
             },
         },
@@ -195,7 +214,19 @@ pub fn spirq_ty_to_dyn(member: &spirq::ty::StructMember, parent_offset: u32) ->
         spirq::ty::Type::SubpassData(_subpass_data_type) => {
             unimplemented!()
         }
-        spirq::ty::Type::Array(_array_type) => unimplemented!("{:?}", _array_type),
+        spirq::ty::Type::Array(array_type) => {
+            let Some(len) = array_type.element_count else {
+                unimplemented!("runtime sized array {:?}", array_type)
+            };
+            let element = spirq_ty_to_dyn(&array_type.element_ty, parent_offset);
+            let stride = array_type.stride.unwrap_or(element.size_of());
+            BaseType::Array(Arc::new(DynLayout::new_array(
+                element,
+                len as usize,
+                stride,
+                parent_offset,
+            )))
+        }
         spirq::ty::Type::Struct(struct_type) => {
             BaseType::Struct(struct_to_layout(struct_type.clone(), parent_offset))
         }
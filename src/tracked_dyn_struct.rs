@@ -1,11 +1,20 @@
-use std::sync::Arc;
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
 
 #[cfg(feature = "bevy_reflect")]
 use bevy_reflect::TypePath;
 
-use bytemuck::{Pod, Zeroable};
+use bytemuck::{bytes_of, pod_read_unaligned, Pod, Zeroable};
 
-use crate::{dyn_layout::DynLayout, dyn_struct::DynStruct, update_bitmask::UpdateBitmask};
+use crate::{
+    aligned_bytes::AlignedBytes,
+    change_stats::StatsState,
+    dyn_layout::DynLayout,
+    dyn_struct::{DynField, DynStruct},
+    update_bitmask::UpdateBitmask,
+};
 
 /// Adds granular change detection tracking on top of DynStruct.
 /// When `get_mut` or `get_mut_raw` are called the offset or path and size_of::<T>() are used to track what regions of
@@ -16,6 +25,10 @@ pub struct TrackedDynStruct {
     pub dyn_struct: DynStruct,
     pub update_bitmask: UpdateBitmask,
     update_stride_exp: usize,
+    /// Changes not yet retrieved by each consumer. See `set_consumer_count`
+    consumers: Vec<UpdateBitmask>,
+    /// See `enable_stats`
+    pub(crate) stats: Option<Box<StatsState>>,
 }
 
 impl TrackedDynStruct {
@@ -32,27 +45,35 @@ impl TrackedDynStruct {
         update_stride: usize,
         update_default: bool,
     ) -> Self {
-        let update_bitmask = UpdateBitmask::new(size_of::<T>() / update_stride, update_default);
+        let update_bitmask =
+            UpdateBitmask::new(size_of::<T>().div_ceil(update_stride), update_default);
         let dyn_struct = DynStruct::new(data, layout);
         TrackedDynStruct {
             dyn_struct,
             update_bitmask,
             update_stride_exp: update_stride.trailing_zeros() as usize,
+            consumers: Vec::new(),
+            stats: None,
         }
     }
 
     pub fn from_bytes(
-        data: Vec<u8>,
+        data: impl Into<AlignedBytes>,
         layout: Arc<DynLayout>,
         update_stride: usize,
         update_default: bool,
     ) -> Self {
-        let update_bitmask = UpdateBitmask::new(data.len() / update_stride, update_default);
         let dyn_struct = DynStruct::from_bytes(data, layout);
+        let update_bitmask = UpdateBitmask::new(
+            dyn_struct.data.len().div_ceil(update_stride),
+            update_default,
+        );
         TrackedDynStruct {
             dyn_struct,
             update_bitmask,
             update_stride_exp: update_stride.trailing_zeros() as usize,
+            consumers: Vec::new(),
+            stats: None,
         }
     }
 
@@ -83,12 +104,118 @@ impl TrackedDynStruct {
         self.dyn_struct.get_mut_raw(offset)
     }
 
+    /// Writes `value` to the field at `path`, only marking the stride blocks whose bytes actually changed.
+    /// Returns None if the path doesn't exist, otherwise whether anything changed.
+    #[inline(always)]
+    pub fn set<T: Pod + Zeroable>(&mut self, path: &[&str], value: T) -> Option<bool> {
+        if let Some(field) = self.dyn_struct.layout.get_path(path) {
+            // If this shouldn't be debug, bring back DynField size, field.ty.size_of() is too slow
+            debug_assert_eq!(size_of::<T>(), field.ty.size_of());
+            Some(self.set_raw(field.offset as usize, value))
+        } else {
+            None
+        }
+    }
+
+    /// Writes `value` at `offset`, only marking the stride blocks whose bytes actually changed.
+    /// Returns whether anything changed.
+    #[inline(always)]
+    pub fn set_raw<T: Pod + Zeroable>(&mut self, offset: usize, value: T) -> bool {
+        let old: T = pod_read_unaligned(&self.dyn_struct.data[offset..offset + size_of::<T>()]);
+        *self.dyn_struct.get_mut_raw(offset) = value;
+        self.mark_differing(offset, bytes_of(&old))
+    }
+
+    /// Like `get_mut`, but instead of marking the field as changed up front the value is snapshotted and only the
+    /// stride blocks that differ from the snapshot are marked when the guard is dropped.
+    #[inline(always)]
+    pub fn get_mut_guarded<T: Pod + Zeroable>(
+        &mut self,
+        path: &[&str],
+    ) -> Option<DiffGuard<'_, T>> {
+        if let Some(field) = self.dyn_struct.layout.get_path(path) {
+            // If this shouldn't be debug, bring back DynField size, field.ty.size_of() is too slow
+            debug_assert_eq!(size_of::<T>(), field.ty.size_of());
+            Some(self.get_mut_raw_guarded(field.offset as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Like `get_mut_raw`, but only marks the stride blocks that changed when the guard is dropped.
+    #[inline(always)]
+    pub fn get_mut_raw_guarded<T: Pod + Zeroable>(&mut self, offset: usize) -> DiffGuard<'_, T> {
+        let snapshot = pod_read_unaligned(&self.dyn_struct.data[offset..offset + size_of::<T>()]);
+        DiffGuard {
+            tracked: self,
+            offset,
+            snapshot,
+        }
+    }
+
+    /// Compares `old` with the current data at `offset` and marks the stride blocks that differ.
+    /// Returns whether anything differed.
+    pub fn mark_differing(&mut self, offset: usize, old: &[u8]) -> bool {
+        let end = offset + old.len();
+        let mut changed = false;
+        for block in self.stride_blocks(offset, old.len()) {
+            let block_start = (block << self.update_stride_exp).max(offset);
+            let block_end = ((block + 1) << self.update_stride_exp).min(end);
+            let new = &self.dyn_struct.data[block_start..block_end];
+            if new != &old[block_start - offset..block_end - offset] {
+                self.mark_blocks(block..block + 1);
+                self.count_marked_bytes(block_end - block_start);
+                changed = true;
+            }
+        }
+        changed
+    }
+
     #[inline(always)]
     /// For manually setting granular change detection. Not needed if using get_mut or get_mut_raw
     pub fn mark_changed<T: Pod + Zeroable>(&mut self, offset: usize) {
+        self.count_marked_bytes(size_of::<T>());
+        self.mark_blocks(self.stride_blocks(offset, size_of::<T>()));
+    }
+
+    #[inline(always)]
+    /// Marks update stride blocks as changed in `update_bitmask` and in every consumer's bitmask. Use this rather than
+    /// setting bits in `update_bitmask` directly when there are consumers.
+    pub fn mark_blocks(&mut self, blocks: std::ops::Range<usize>) {
+        self.update_bitmask.set(blocks.clone());
+        for consumer in &mut self.consumers {
+            consumer.set(blocks.clone());
+        }
+    }
+
+    #[inline(always)]
+    /// Range of update stride blocks that overlap `size` bytes at `offset`
+    pub fn stride_blocks(&self, offset: usize, size: usize) -> std::ops::Range<usize> {
+        let stride_mask = (1 << self.update_stride_exp) - 1;
         let bitmask_start = offset >> self.update_stride_exp;
-        let bitmask_end = (offset + size_of::<T>()) >> self.update_stride_exp;
-        self.update_bitmask.set(bitmask_start..bitmask_end);
+        let bitmask_end = (offset + size + stride_mask) >> self.update_stride_exp;
+        bitmask_start..bitmask_end
+    }
+
+    #[inline(always)]
+    pub fn update_stride(&self) -> usize {
+        1 << self.update_stride_exp
+    }
+
+    /// Leaf fields (see `DynLayout::leaves`) that overlap a region marked as changed, with their path.
+    /// Changes are tracked with `update_stride` granularity, so fields that share a stride block with a changed field
+    /// are also included.
+    /// if instance.changed_fields().any(|(path, _)| path == "material_index") { ... }
+    pub fn changed_fields(&self) -> impl Iterator<Item = (&str, &DynField)> {
+        self.dyn_struct
+            .layout
+            .leaves()
+            .iter()
+            .filter(|(_, field)| {
+                let blocks = self.stride_blocks(field.offset as usize, field.ty.size_of());
+                self.update_bitmask.any_in_range(blocks)
+            })
+            .map(|(path, field)| (path.as_str(), field))
     }
 
     /// dyn_struct.retrieve_changes(|data_slice, start, end| {
@@ -96,31 +223,27 @@ impl TrackedDynStruct {
     ///     indices.extend((dst_offset + start)..(dst_offset + end));
     /// });
     #[inline(always)]
-    pub fn retrieve_changes<T: bytemuck::Pod>(&self, mut extend_fn: impl FnMut(&[T], u32, u32)) {
-        if !self.update_bitmask.any_set() || self.dyn_struct.data.is_empty() {
-            return;
-        }
+    pub fn retrieve_changes<T: bytemuck::Pod>(&self, extend_fn: impl FnMut(&[T], u32, u32)) {
+        retrieve_bitmask_changes(&self.dyn_struct.data, &self.update_bitmask, extend_fn);
+    }
 
-        let src_data: &[T] = bytemuck::cast_slice(&self.dyn_struct.data);
-        let data_length = src_data.len();
-
-        for (chunk_n, chunk) in self.update_bitmask.bits.iter().enumerate() {
-            let chunk_index = chunk_n << 4;
-            let mut chunk = *chunk;
-            let mut total = 0;
-            while chunk != 0 {
-                // Start from LSB and chip away at chunk while making copies the size of contiguous ones
-                let count = chunk.trailing_ones() as usize;
-                let start = chunk_index + total;
-                let end = (chunk_index + total + count).min(data_length);
-                extend_fn(&src_data[start..end], start as u32, end as u32);
-                total += count;
-                chunk = chunk.checked_shr(count as u32).unwrap_or(0);
-                let zeros_count = chunk.trailing_zeros();
-                chunk = chunk.checked_shr(zeros_count).unwrap_or(0);
-                total += zeros_count as usize;
-            }
-        }
+    /// Like `retrieve_changes` but calls `extend_fn` once per run of changed blocks even when the run spans several
+    /// bitmask chunks. Runs separated by at most `max_gap` unchanged blocks are merged and every run is extended to
+    /// at least `min_run` blocks, so fewer but larger copies are made. See `UpdateBitmask::coalesced_ranges`.
+    #[inline(always)]
+    pub fn retrieve_changes_coalesced<T: bytemuck::Pod>(
+        &self,
+        max_gap: usize,
+        min_run: usize,
+        extend_fn: impl FnMut(&[T], u32, u32),
+    ) {
+        retrieve_bitmask_ranges(
+            &self.dyn_struct.data,
+            &self.update_bitmask,
+            max_gap,
+            min_run,
+            extend_fn,
+        );
     }
 
     #[inline(always)]
@@ -132,6 +255,7 @@ impl TrackedDynStruct {
         self.reset_change_detection();
     }
 
+    /// Whether anything was marked since the last `reset_change_detection`
     #[inline(always)]
     pub fn changed(&self) -> bool {
         self.update_bitmask.any_set()
@@ -139,6 +263,162 @@ impl TrackedDynStruct {
 
     #[inline(always)]
     pub fn reset_change_detection(&mut self) {
+        self.record_stats_frame();
         self.update_bitmask.reset();
     }
+
+    /// Track changes separately for `count` consumers, for example one per frame in flight, where each GPU copy of
+    /// the retained buffer needs every change made since that copy was last updated.
+    /// New consumers start with everything marked as changed since they haven't received any data yet.
+    /// Consumers are marked at the same time as `update_bitmask` but are otherwise independent of it: `ack` doesn't
+    /// clear `update_bitmask` and `reset_change_detection` doesn't clear the consumers.
+    pub fn set_consumer_count(&mut self, count: usize) {
+        let blocks = self.dyn_struct.data.len().div_ceil(self.update_stride());
+        self.consumers
+            .resize_with(count, || UpdateBitmask::new(blocks, true));
+    }
+
+    #[inline(always)]
+    pub fn consumer_count(&self) -> usize {
+        self.consumers.len()
+    }
+
+    /// Changes `consumer` hasn't acknowledged yet.
+    #[inline(always)]
+    pub fn consumer_bitmask(&self, consumer: usize) -> &UpdateBitmask {
+        &self.consumers[consumer]
+    }
+
+    #[inline(always)]
+    pub fn consumer_changed(&self, consumer: usize) -> bool {
+        self.consumers[consumer].any_set()
+    }
+
+    /// Like `retrieve_changes` but returns every change made since `consumer` was last acknowledged with `ack`.
+    #[inline(always)]
+    pub fn retrieve_changes_for<T: bytemuck::Pod>(
+        &self,
+        consumer: usize,
+        extend_fn: impl FnMut(&[T], u32, u32),
+    ) {
+        retrieve_bitmask_changes(&self.dyn_struct.data, &self.consumers[consumer], extend_fn);
+    }
+
+    /// `retrieve_changes_coalesced` for `consumer`, see `retrieve_changes_for`.
+    #[inline(always)]
+    pub fn retrieve_changes_coalesced_for<T: bytemuck::Pod>(
+        &self,
+        consumer: usize,
+        max_gap: usize,
+        min_run: usize,
+        extend_fn: impl FnMut(&[T], u32, u32),
+    ) {
+        retrieve_bitmask_ranges(
+            &self.dyn_struct.data,
+            &self.consumers[consumer],
+            max_gap,
+            min_run,
+            extend_fn,
+        );
+    }
+
+    /// Mark `consumer` as up to date.
+    #[inline(always)]
+    pub fn ack(&mut self, consumer: usize) {
+        self.consumers[consumer].reset();
+    }
+
+    #[inline(always)]
+    pub fn retrieve_changes_for_and_ack<T: bytemuck::Pod>(
+        &mut self,
+        consumer: usize,
+        extend_fn: impl FnMut(&[T], u32, u32),
+    ) {
+        self.retrieve_changes_for(consumer, extend_fn);
+        self.ack(consumer);
+    }
+}
+
+#[inline(always)]
+fn retrieve_bitmask_changes<T: bytemuck::Pod>(
+    data: &[u8],
+    bitmask: &UpdateBitmask,
+    mut extend_fn: impl FnMut(&[T], u32, u32),
+) {
+    if !bitmask.any_set() || data.is_empty() {
+        return;
+    }
+
+    let src_data: &[T] = bytemuck::cast_slice(data);
+    let data_length = src_data.len();
+
+    for (chunk_n, chunk) in bitmask.bits.iter().enumerate() {
+        let chunk_index = chunk_n << 4;
+        let mut chunk = *chunk;
+        let mut total = 0;
+        while chunk != 0 {
+            // Start from LSB and chip away at chunk while making copies the size of contiguous ones
+            let zeros_count = chunk.trailing_zeros();
+            chunk >>= zeros_count;
+            total += zeros_count as usize;
+            let count = chunk.trailing_ones() as usize;
+            let start = chunk_index + total;
+            let end = (chunk_index + total + count).min(data_length);
+            extend_fn(&src_data[start..end], start as u32, end as u32);
+            total += count;
+            chunk = chunk.checked_shr(count as u32).unwrap_or(0);
+        }
+    }
+}
+
+#[inline(always)]
+fn retrieve_bitmask_ranges<T: bytemuck::Pod>(
+    data: &[u8],
+    bitmask: &UpdateBitmask,
+    max_gap: usize,
+    min_run: usize,
+    mut extend_fn: impl FnMut(&[T], u32, u32),
+) {
+    if !bitmask.any_set() || data.is_empty() {
+        return;
+    }
+
+    let src_data: &[T] = bytemuck::cast_slice(data);
+    let data_length = src_data.len();
+
+    for range in bitmask.coalesced_ranges(max_gap, min_run) {
+        let end = range.end.min(data_length);
+        extend_fn(&src_data[range.start..end], range.start as u32, end as u32);
+    }
+}
+
+/// Mutable access to a field of a `TrackedDynStruct` that marks only the bytes that changed once dropped.
+/// See `TrackedDynStruct::get_mut_guarded`
+pub struct DiffGuard<'a, T: Pod + Zeroable> {
+    tracked: &'a mut TrackedDynStruct,
+    offset: usize,
+    snapshot: T,
+}
+
+impl<T: Pod + Zeroable> Deref for DiffGuard<'_, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        self.tracked.dyn_struct.get_raw(self.offset)
+    }
+}
+
+impl<T: Pod + Zeroable> DerefMut for DiffGuard<'_, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        self.tracked.dyn_struct.get_mut_raw(self.offset)
+    }
+}
+
+impl<T: Pod + Zeroable> Drop for DiffGuard<'_, T> {
+    fn drop(&mut self) {
+        self.tracked
+            .mark_differing(self.offset, bytes_of(&self.snapshot));
+    }
 }
@@ -1,17 +1,28 @@
+use std::{iter::Peekable, ops::Range};
+
 use smallvec::{smallvec, SmallVec};
 
 #[derive(Clone, Debug, Default)]
 pub struct UpdateBitmask {
     pub bits: SmallVec<u16, 8>,
     pub any: bool,
+    /// Number of bits in use, the rest of the last word is always 0
+    pub len: usize,
 }
 
 impl UpdateBitmask {
     #[inline]
     pub fn new(size: usize, default: bool) -> Self {
-        let default_val = if default { u16::MAX } else { 0 };
-        let bits = smallvec![default_val; (size + 15) >> 4]; // (size + 7) / 8
-        UpdateBitmask { bits, any: default }
+        let bits = smallvec![0; (size + 15) >> 4]; // (size + 15) / 16
+        let mut bitmask = UpdateBitmask {
+            bits,
+            any: false,
+            len: size,
+        };
+        if default {
+            bitmask.set_all();
+        }
+        bitmask
     }
 
     #[inline]
@@ -23,8 +34,7 @@ impl UpdateBitmask {
 
     #[inline]
     pub fn set_all(&mut self) {
-        self.bits.fill(u16::MAX);
-        self.any = true;
+        self.set(0..self.len);
     }
 
     #[inline]
@@ -48,10 +58,182 @@ impl UpdateBitmask {
     }
 
     #[inline]
-    pub fn set(&mut self, range: std::ops::Range<usize>) {
-        // TODO more efficiently handle setting larger ranges (bitmap blit?)
-        for i in range {
-            self.set_one(i)
+    /// Sets every bit in `range`, a whole word at a time
+    pub fn set(&mut self, range: Range<usize>) {
+        for (u16_index, mask) in word_masks(range) {
+            self.bits[u16_index] |= mask;
+            self.any = true;
+        }
+    }
+
+    #[inline]
+    /// Clears every bit in `range`, a whole word at a time
+    pub fn clear_range(&mut self, range: Range<usize>) {
+        for (u16_index, mask) in word_masks(range) {
+            self.bits[u16_index] &= !mask;
+        }
+        self.update_any();
+    }
+
+    #[inline]
+    /// True if any bit in `range` is set
+    pub fn any_in_range(&self, range: Range<usize>) -> bool {
+        self.any && word_masks(range).any(|(u16_index, mask)| self.bits[u16_index] & mask != 0)
+    }
+
+    #[inline]
+    /// Number of set bits
+    pub fn count_ones(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    #[inline]
+    /// Sets every bit that is set in `other`. Panics if the bitmasks are different sizes.
+    pub fn union_with(&mut self, other: &UpdateBitmask) {
+        assert_eq!(self.len, other.len, "bitmasks have different sizes");
+        for (a, b) in self.bits.iter_mut().zip(other.bits.iter()) {
+            *a |= *b;
+        }
+        self.any |= other.any;
+    }
+
+    #[inline]
+    /// Clears every bit that isn't set in `other`. Panics if the bitmasks are different sizes.
+    pub fn intersect_with(&mut self, other: &UpdateBitmask) {
+        assert_eq!(self.len, other.len, "bitmasks have different sizes");
+        for (a, b) in self.bits.iter_mut().zip(other.bits.iter()) {
+            *a &= *b;
+        }
+        self.update_any();
+    }
+
+    #[inline]
+    /// Clears every bit that is set in `other`. Panics if the bitmasks are different sizes.
+    pub fn difference_with(&mut self, other: &UpdateBitmask) {
+        assert_eq!(self.len, other.len, "bitmasks have different sizes");
+        for (a, b) in self.bits.iter_mut().zip(other.bits.iter()) {
+            *a &= !*b;
+        }
+        self.update_any();
+    }
+
+    /// Iterates over the contiguous runs of set bits. Unlike looping over `bits` a run can span several words.
+    pub fn ranges(&self) -> Ranges<'_> {
+        Ranges {
+            bitmask: self,
+            index: 0,
+        }
+    }
+
+    /// Like `ranges` but merges runs separated by at most `max_gap` clear bits and extends every run to at least
+    /// `min_run` bits (clamped to `len`). Trades copying some unchanged data for fewer, larger copies.
+    pub fn coalesced_ranges(&self, max_gap: usize, min_run: usize) -> CoalescedRanges<'_> {
+        CoalescedRanges {
+            ranges: self.ranges().peekable(),
+            len: self.len,
+            max_gap,
+            min_run,
+        }
+    }
+
+    /// Index of the first bit at or after `from` that equals `value`, or `len` if there is none
+    fn next_bit(&self, from: usize, value: bool) -> usize {
+        let mut u16_index = from >> 4;
+        let mut mask = u16::MAX << (from % 16);
+        while let Some(word) = self.bits.get(u16_index) {
+            let word = if value { *word } else { !*word } & mask;
+            if word != 0 {
+                let index = (u16_index << 4) + word.trailing_zeros() as usize;
+                return index.min(self.len);
+            }
+            u16_index += 1;
+            mask = u16::MAX;
+        }
+        self.len
+    }
+
+    #[inline]
+    fn update_any(&mut self) {
+        self.any = self.bits.iter().any(|word| *word != 0);
+    }
+}
+
+/// Splits `range` into the index of each word it touches and the mask of its bits in that word
+#[inline]
+fn word_masks(range: Range<usize>) -> impl Iterator<Item = (usize, u16)> {
+    let first = range.start >> 4;
+    let last = range.end.saturating_sub(1) >> 4;
+    let words = if range.is_empty() {
+        first..first
+    } else {
+        first..last + 1
+    };
+    words.map(move |u16_index| {
+        let lo = if u16_index == first {
+            range.start % 16
+        } else {
+            0
+        };
+        let hi = if u16_index == last {
+            (range.end - 1) % 16 + 1
+        } else {
+            16
+        };
+        (u16_index, (u16::MAX >> (16 - (hi - lo))) << lo)
+    })
+}
+
+/// Iterator over the contiguous runs of set bits, see `UpdateBitmask::ranges`
+#[derive(Clone, Debug)]
+pub struct Ranges<'a> {
+    bitmask: &'a UpdateBitmask,
+    index: usize,
+}
+
+impl Iterator for Ranges<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.bitmask.any || self.index >= self.bitmask.len {
+            return None;
+        }
+        let start = self.bitmask.next_bit(self.index, true);
+        if start >= self.bitmask.len {
+            self.index = start;
+            return None;
+        }
+        let end = self.bitmask.next_bit(start, false);
+        self.index = end;
+        Some(start..end)
+    }
+}
+
+/// Iterator over runs of set bits with small gaps bridged, see `UpdateBitmask::coalesced_ranges`
+#[derive(Clone, Debug)]
+pub struct CoalescedRanges<'a> {
+    ranges: Peekable<Ranges<'a>>,
+    len: usize,
+    max_gap: usize,
+    min_run: usize,
+}
+
+impl Iterator for CoalescedRanges<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut run = self.ranges.next()?;
+        loop {
+            run.end = run.end.max((run.start + self.min_run).min(self.len));
+            match self.ranges.peek() {
+                Some(next) if next.start <= run.end + self.max_gap => {
+                    run.end = run.end.max(next.end);
+                    self.ranges.next();
+                }
+                _ => return Some(run),
+            }
         }
     }
 }
@@ -0,0 +1,212 @@
+use std::ops::Range;
+
+use crate::{
+    change_stats::ChangeStats, tracked_dyn_struct::TrackedDynStruct, upload_plan::UploadPlan,
+};
+
+/// Gathers the changes of many `TrackedDynStruct`s that live in one retained GPU buffer into the buffers read by the
+/// scatter shader (see `scatter_shader`):
+/// - `src`: `UPDATE_STRIDE` words of new data per update
+/// - `update_indices`: the index of the `UPDATE_STRIDE` sized block in the retained buffer each update is copied to
+///
+/// let mut batch = RetainedUploadBatch::new(16);
+/// for (dst_offset, instance) in instances.iter_mut() {
+///     batch.add(*dst_offset, instance);
+/// }
+/// upload(&batch.src, &batch.update_indices);
+/// dispatch(batch.len().div_ceil(64));
+#[derive(Clone, Debug)]
+pub struct RetainedUploadBatch {
+    update_stride: usize,
+    pub src: Vec<u32>,
+    pub update_indices: Vec<u32>,
+    pub stats: UploadStats,
+    /// `ChangeStats` of this batch merged from every struct added that has stats enabled
+    pub change_stats: ChangeStats,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UploadStats {
+    /// Number of structs added to the batch
+    pub structs: usize,
+    /// Number of structs that had any changes
+    pub changed_structs: usize,
+    /// Total size of every struct added, what a full upload would copy
+    pub tracked_bytes: usize,
+    /// Bytes of changed data in `src`
+    pub src_bytes: usize,
+    /// Bytes of `update_indices`
+    pub index_bytes: usize,
+}
+
+impl UploadStats {
+    /// Bytes that have to be uploaded to the GPU for this batch
+    pub fn upload_bytes(&self) -> usize {
+        self.src_bytes + self.index_bytes
+    }
+
+    /// Bytes saved compared to uploading every struct in full. Negative when almost everything changed, since
+    /// indices are uploaded too.
+    pub fn saved_bytes(&self) -> isize {
+        self.tracked_bytes as isize - self.upload_bytes() as isize
+    }
+}
+
+impl RetainedUploadBatch {
+    /// `update_stride` is the `UPDATE_STRIDE` of the shader in bytes and must match the stride of every struct
+    /// added. Panics if it isn't a power of 2 and a multiple of 4.
+    pub fn new(update_stride: usize) -> Self {
+        assert!(
+            update_stride.is_power_of_two() && update_stride >= 4,
+            "Update stride {update_stride} must be a power of 2 and a multiple of 4"
+        );
+        RetainedUploadBatch {
+            update_stride,
+            src: Vec::new(),
+            update_indices: Vec::new(),
+            stats: UploadStats::default(),
+            change_stats: ChangeStats::default(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn update_stride(&self) -> usize {
+        self.update_stride
+    }
+
+    /// Number of `u32` words per update, `UPDATE_STRIDE` in the shader
+    #[inline(always)]
+    pub fn stride_words(&self) -> usize {
+        self.update_stride / 4
+    }
+
+    /// Number of updates, the thread count to dispatch
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.update_indices.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.update_indices.is_empty()
+    }
+
+    /// Gathers the changes of `tracked`, which lives `dst_offset` bytes into the retained buffer, and resets its
+    /// change detection.
+    /// Panics if `dst_offset` or the struct size aren't multiples of the update stride, or the struct uses a
+    /// different update stride.
+    pub fn add(&mut self, dst_offset: usize, tracked: &mut TrackedDynStruct) {
+        self.add_struct(dst_offset, tracked);
+        if tracked.changed() {
+            self.stats.changed_structs += 1;
+            let dst_block = dst_offset / self.update_stride;
+            for range in tracked.update_bitmask.ranges() {
+                self.push_blocks(dst_block, &tracked.dyn_struct.data, range);
+            }
+        }
+        self.end_frame(tracked);
+    }
+
+    /// Like `add` but gathers the byte ranges of `plan` (see `TrackedDynStruct::plan_upload`) instead of the changed
+    /// blocks. Ranges are rounded out to the update stride.
+    pub fn add_plan(
+        &mut self,
+        dst_offset: usize,
+        tracked: &mut TrackedDynStruct,
+        plan: &UploadPlan,
+    ) {
+        self.add_struct(dst_offset, tracked);
+        if !plan.is_empty() {
+            self.stats.changed_structs += 1;
+            let stride = self.update_stride;
+            let dst_block = dst_offset / stride;
+            for range in plan.byte_ranges(tracked.dyn_struct.data.len()) {
+                let blocks = range.start / stride..range.end.div_ceil(stride);
+                self.push_blocks(dst_block, &tracked.dyn_struct.data, blocks);
+            }
+        }
+        self.end_frame(tracked);
+    }
+
+    /// Resets the change detection of `tracked`, recording a stats frame even if nothing changed
+    fn end_frame(&mut self, tracked: &mut TrackedDynStruct) {
+        tracked.reset_change_detection();
+        if let Some(frame) = tracked.last_frame_stats() {
+            self.change_stats.merge(frame);
+        }
+    }
+
+    fn add_struct(&mut self, dst_offset: usize, tracked: &TrackedDynStruct) {
+        let stride = self.update_stride;
+        assert_eq!(
+            tracked.update_stride(),
+            stride,
+            "Struct update stride doesn't match the batch"
+        );
+        assert!(
+            dst_offset.is_multiple_of(stride),
+            "Destination offset {dst_offset} isn't a multiple of the update stride {stride}"
+        );
+        let size = tracked.dyn_struct.data.len();
+        assert!(
+            size.is_multiple_of(stride),
+            "Struct size {size} isn't a multiple of the update stride {stride}"
+        );
+
+        self.stats.structs += 1;
+        self.stats.tracked_bytes += size;
+    }
+
+    fn push_blocks(&mut self, dst_block: usize, data: &[u8], blocks: Range<usize>) {
+        let stride = self.update_stride;
+        let bytes = &data[blocks.start * stride..blocks.end * stride];
+        let start = self.src.len();
+        self.src.resize(start + bytes.len() / 4, 0);
+        bytemuck::cast_slice_mut::<u32, u8>(&mut self.src[start..]).copy_from_slice(bytes);
+        self.update_indices
+            .extend(blocks.map(|block| (dst_block + block) as u32));
+        self.stats.src_bytes = self.src.len() * 4;
+        self.stats.index_bytes = self.update_indices.len() * 4;
+    }
+
+    /// `add` for every `(dst_offset, tracked)` pair
+    pub fn extend<'a>(
+        &mut self,
+        structs: impl IntoIterator<Item = (usize, &'a mut TrackedDynStruct)>,
+    ) {
+        for (dst_offset, tracked) in structs {
+            self.add(dst_offset, tracked);
+        }
+    }
+
+    /// Clears the batch so it can be reused for the next frame without reallocating.
+    pub fn reset(&mut self) {
+        self.src.clear();
+        self.update_indices.clear();
+        self.stats = UploadStats::default();
+        self.change_stats = ChangeStats::default();
+    }
+
+    #[inline(always)]
+    pub fn src_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.src)
+    }
+
+    #[inline(always)]
+    pub fn update_indices_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.update_indices)
+    }
+
+    /// Applies the batch to a CPU copy of the retained buffer, the same thing the scatter shader does on the GPU.
+    pub fn apply(&self, retained: &mut [u8]) {
+        let stride = self.update_stride;
+        for (src, dst_block) in self
+            .src_bytes()
+            .chunks_exact(stride)
+            .zip(&self.update_indices)
+        {
+            let dst = *dst_block as usize * stride;
+            retained[dst..dst + stride].copy_from_slice(src);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::ops::Range;
+
+use crate::{tracked_dyn_struct::TrackedDynStruct, update_bitmask::UpdateBitmask};
+
+/// Tunable costs used to pick an `UploadPlan`, in arbitrary units. The defaults use the cost of uploading one byte as
+/// the unit. Profile on the target hardware to tune them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UploadCosts {
+    /// Fixed cost of each copy command or `write_buffer` call
+    pub per_copy: f32,
+    /// Cost of uploading one byte
+    pub per_byte: f32,
+    /// Fixed cost of scattering with the scatter shader. When many structs share one `RetainedUploadBatch` use
+    /// this struct's share of the dispatch.
+    pub per_dispatch: f32,
+}
+
+impl Default for UploadCosts {
+    fn default() -> Self {
+        UploadCosts {
+            per_copy: 512.0,
+            per_byte: 1.0,
+            per_dispatch: 2048.0,
+        }
+    }
+}
+
+/// Estimated cost of each way of uploading the changes, see `UploadCosts::estimate`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UploadEstimate {
+    pub scatter: f32,
+    pub ranges: f32,
+    pub full: f32,
+}
+
+/// How to upload the changes of a struct. Byte ranges are relative to the start of the struct.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UploadPlan {
+    /// Scatter the changed update stride blocks with the scatter shader. See `RetainedUploadBatch::add_plan`.
+    Scatter(Vec<Range<usize>>),
+    /// Copy each byte range, small gaps between changes are bridged. Empty if nothing changed.
+    Ranges(Vec<Range<usize>>),
+    /// Upload the whole struct
+    Full,
+}
+
+impl UploadCosts {
+    /// Largest number of unchanged bytes worth copying to save a copy command
+    pub fn max_gap_bytes(&self) -> usize {
+        (self.per_copy / self.per_byte.max(f32::EPSILON)) as usize
+    }
+
+    /// Estimates the cost of every upload method for the blocks set in `bitmask`. `size` is the size of the struct.
+    pub fn estimate(
+        &self,
+        bitmask: &UpdateBitmask,
+        update_stride: usize,
+        size: usize,
+    ) -> UploadEstimate {
+        let changed_blocks = bitmask.count_ones();
+        let changed_bytes = (changed_blocks * update_stride).min(size);
+        // src data and indices are uploaded as one copy each
+        let scatter = self.per_dispatch
+            + 2.0 * self.per_copy
+            + (changed_bytes + changed_blocks * 4) as f32 * self.per_byte;
+
+        let ranges = self.ranges(bitmask, update_stride, size);
+        let range_bytes = ranges.iter().map(|range| range.len()).sum::<usize>();
+        let ranges = ranges.len() as f32 * self.per_copy + range_bytes as f32 * self.per_byte;
+
+        let full = self.per_copy + size as f32 * self.per_byte;
+        UploadEstimate {
+            scatter,
+            ranges,
+            full,
+        }
+    }
+
+    /// Picks the cheapest way to upload the blocks set in `bitmask`
+    pub fn plan(&self, bitmask: &UpdateBitmask, update_stride: usize, size: usize) -> UploadPlan {
+        if !bitmask.any_set() {
+            return UploadPlan::Ranges(Vec::new());
+        }
+        let estimate = self.estimate(bitmask, update_stride, size);
+        // Prefer the simpler plan on ties
+        if estimate.full <= estimate.ranges && estimate.full <= estimate.scatter {
+            UploadPlan::Full
+        } else if estimate.ranges <= estimate.scatter {
+            UploadPlan::Ranges(self.ranges(bitmask, update_stride, size))
+        } else {
+            let ranges = bitmask
+                .ranges()
+                .map(|range| to_bytes(range, update_stride, size))
+                .collect();
+            UploadPlan::Scatter(ranges)
+        }
+    }
+
+    fn ranges(
+        &self,
+        bitmask: &UpdateBitmask,
+        update_stride: usize,
+        size: usize,
+    ) -> Vec<Range<usize>> {
+        let max_gap = self.max_gap_bytes() / update_stride;
+        bitmask
+            .coalesced_ranges(max_gap, 0)
+            .map(|range| to_bytes(range, update_stride, size))
+            .collect()
+    }
+}
+
+#[inline(always)]
+fn to_bytes(blocks: Range<usize>, update_stride: usize, size: usize) -> Range<usize> {
+    (blocks.start * update_stride).min(size)..(blocks.end * update_stride).min(size)
+}
+
+impl UploadPlan {
+    /// True if there is nothing to upload
+    pub fn is_empty(&self) -> bool {
+        match self {
+            UploadPlan::Scatter(ranges) | UploadPlan::Ranges(ranges) => ranges.is_empty(),
+            UploadPlan::Full => false,
+        }
+    }
+
+    /// The byte ranges to upload, `0..size` for `Full`
+    #[allow(clippy::single_range_in_vec_init)]
+    pub fn byte_ranges(&self, size: usize) -> Vec<Range<usize>> {
+        match self {
+            UploadPlan::Scatter(ranges) | UploadPlan::Ranges(ranges) => ranges.clone(),
+            UploadPlan::Full => vec![0..size],
+        }
+    }
+
+    /// Calls `copy_fn` with the offset and bytes of each range to upload, for example to call `write_buffer` at
+    /// `dst_offset + offset`. Scatter plans are copied range by range.
+    pub fn for_each_copy(&self, data: &[u8], mut copy_fn: impl FnMut(usize, &[u8])) {
+        match self {
+            UploadPlan::Scatter(ranges) | UploadPlan::Ranges(ranges) => {
+                for range in ranges {
+                    copy_fn(range.start, &data[range.clone()]);
+                }
+            }
+            UploadPlan::Full => copy_fn(0, data),
+        }
+    }
+}
+
+impl TrackedDynStruct {
+    /// Picks the cheapest way to upload the changes marked since the last reset, see `UploadCosts::plan`
+    pub fn plan_upload(&self, costs: &UploadCosts) -> UploadPlan {
+        costs.plan(
+            &self.update_bitmask,
+            self.update_stride(),
+            self.dyn_struct.data.len(),
+        )
+    }
+
+    /// `plan_upload` for the changes `consumer` hasn't acknowledged yet
+    pub fn plan_upload_for(&self, consumer: usize, costs: &UploadCosts) -> UploadPlan {
+        costs.plan(
+            self.consumer_bitmask(consumer),
+            self.update_stride(),
+            self.dyn_struct.data.len(),
+        )
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt::{self, Display};
+
+use crate::{
+    dyn_layout::{join_path, DynLayout},
+    dyn_struct::DynField,
+    packing::{PackingRules, TypeShape},
+};
+
+/// A field that breaks the packing rules a layout was validated against.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LayoutViolation {
+    /// Path to the field, separated by `.` (e.g. "nested.a" or "lights.0.color")
+    pub path: String,
+    /// Absolute offset of the field in bytes
+    pub offset: usize,
+    pub kind: ViolationKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ViolationKind {
+    /// The offset of the field (relative to the start of the layout) is not a multiple of `align`.
+    Misaligned { align: usize },
+    /// The field starts before `previous` ends. The end includes any padding the rules add to `previous`, like the
+    /// trailing padding of a std140 struct.
+    Overlap { previous: String, end: usize },
+    /// HLSL cbuffer fields can't cross a 16 byte register boundary.
+    StraddlesRegister,
+    /// Matrix columns are `actual` bytes apart in the rust type but the rules require `expected`.
+    MatrixStride { expected: usize, actual: usize },
+    /// Array elements are `actual` bytes apart but the rules require `expected`.
+    ArrayStride { expected: usize, actual: usize },
+}
+
+impl Display for LayoutViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (offset {}): ", self.path, self.offset)?;
+        match &self.kind {
+            ViolationKind::Misaligned { align } => write!(f, "not aligned to {align} bytes"),
+            ViolationKind::Overlap { previous, end } => {
+                write!(f, "overlaps {previous} which ends at offset {end}")
+            }
+            ViolationKind::StraddlesRegister => write!(f, "crosses a 16 byte register boundary"),
+            ViolationKind::MatrixStride { expected, actual } => write!(
+                f,
+                "matrix columns are {actual} bytes apart but must be {expected} bytes apart"
+            ),
+            ViolationKind::ArrayStride { expected, actual } => write!(
+                f,
+                "array elements are {actual} bytes apart but must be {expected} bytes apart"
+            ),
+        }
+    }
+}
+
+impl DynLayout {
+    /// Checks every field (recursively) against the packing rules. Returns an empty Vec if the layout is legal.
+    /// The same layout can be fine in a storage buffer (std430) but not in a uniform buffer (std140).
+    /// Offsets are checked relative to the start of this layout (the offset of its first field).
+    pub fn validate(&self, rules: PackingRules) -> Vec<LayoutViolation> {
+        let mut violations = Vec::new();
+        let base = self.fields.first().map(|(_, f)| f.offset).unwrap_or(0) as usize;
+        validate_fields(self, rules, base, "", &mut violations);
+        violations
+    }
+}
+
+fn validate_fields(
+    layout: &DynLayout,
+    rules: PackingRules,
+    base: usize,
+    prefix: &str,
+    violations: &mut Vec<LayoutViolation>,
+) {
+    let mut previous: Option<(String, usize)> = None;
+    for (name, field) in &layout.fields {
+        let path = join_path(prefix, name);
+        let offset = field.offset as usize;
+        let relative = offset.wrapping_sub(base);
+        let align = rules.align_of(&field.ty);
+        let size = rules.size_of(&field.ty);
+        let mut violation = |kind| {
+            violations.push(LayoutViolation {
+                path: path.clone(),
+                offset,
+                kind,
+            })
+        };
+        if !relative.is_multiple_of(align) {
+            violation(ViolationKind::Misaligned { align });
+        } else if rules == PackingRules::HlslCbuffer
+            && size > 0
+            && size <= 16
+            && relative / 16 != (relative + size - 1) / 16
+        {
+            violation(ViolationKind::StraddlesRegister);
+        }
+        if let Some((previous, end)) = previous.take() {
+            if offset < end {
+                violation(ViolationKind::Overlap { previous, end });
+            }
+        }
+        validate_type(field, rules, base, &path, violations);
+        previous = Some((path, offset + size));
+    }
+}
+
+/// Checks the inside of a type (matrix columns, struct fields, array elements)
+fn validate_type(
+    field: &DynField,
+    rules: PackingRules,
+    base: usize,
+    path: &str,
+    violations: &mut Vec<LayoutViolation>,
+) {
+    match field.ty.shape() {
+        TypeShape::Matrix {
+            component_size,
+            rows,
+            ..
+        } => {
+            let expected = rules.matrix_column_stride(component_size, rows);
+            let actual = component_size * rows;
+            if expected != actual {
+                violations.push(LayoutViolation {
+                    path: path.to_string(),
+                    offset: field.offset as usize,
+                    kind: ViolationKind::MatrixStride { expected, actual },
+                });
+            }
+        }
+        TypeShape::Struct(layout) => validate_fields(layout, rules, base, path, violations),
+        TypeShape::Array(layout) => {
+            let Some((name, element)) = layout.fields.first() else {
+                return;
+            };
+            let expected = rules.array_stride(&element.ty);
+            let actual = layout.array_stride();
+            if expected != actual {
+                violations.push(LayoutViolation {
+                    path: path.to_string(),
+                    offset: field.offset as usize,
+                    kind: ViolationKind::ArrayStride { expected, actual },
+                });
+            }
+            // Every element has the same type, so only the contents of the first one need to be checked.
+            validate_type(element, rules, base, &join_path(path, name), violations);
+        }
+        _ => (),
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    fmt::{self, Display},
+    io::{self, Write},
+};
+
+use crate::{
+    dyn_struct::DynStruct,
+    dyn_value::{DynValue, Scalar},
+};
+
+/// A leaf field whose value differs between two structs, see `DynStruct::diff_values`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueChange {
+    pub path: String,
+    pub old: DynValue,
+    pub new: DynValue,
+}
+
+impl Display for ValueChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.path, self.old, self.new)
+    }
+}
+
+/// Float comparison for `DynStruct::diff_values_with`. The default compares exactly with `nan_equal`, so a field that
+/// stays NaN isn't reported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffOptions {
+    /// Floats closer than this are equal. 0.0 compares exactly.
+    pub epsilon: f64,
+    /// Treat NaN as equal to NaN. Otherwise a NaN field is always reported.
+    pub nan_equal: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            epsilon: 0.0,
+            nan_equal: true,
+        }
+    }
+}
+
+impl DiffOptions {
+    fn scalar_eq(&self, a: &Scalar, b: &Scalar) -> bool {
+        match (a, b) {
+            (Scalar::Float(a), Scalar::Float(b)) if a.is_nan() || b.is_nan() => {
+                self.nan_equal && a.is_nan() && b.is_nan()
+            }
+            (Scalar::Float(a), Scalar::Float(b)) => (a - b).abs() <= self.epsilon,
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl DynStruct {
+    /// Leaf fields (see `DynLayout::leaves`) whose values differ from `other`, with exact float comparison except
+    /// that NaN equals NaN (see `DiffOptions::default`). Panics if the layouts differ.
+    pub fn diff_values(&self, other: &DynStruct) -> Vec<ValueChange> {
+        self.diff_values_with(other, &DiffOptions::default())
+    }
+
+    /// `diff_values` with float epsilon and NaN handling from `options`. `self` is the old value.
+    pub fn diff_values_with(&self, other: &DynStruct, options: &DiffOptions) -> Vec<ValueChange> {
+        assert!(
+            self.layout == other.layout,
+            "Can't diff structs with different layouts ({} and {})",
+            self.layout.name,
+            other.layout.name
+        );
+        let mut changes = Vec::new();
+        for (path, field) in self.layout.leaves() {
+            let offset = field.offset as usize;
+            let range = offset..offset + field.ty.size_of();
+            // Identical bytes can still hold a NaN that must be reported
+            if options.nan_equal && self.data[range.clone()] == other.data[range] {
+                continue;
+            }
+            let (Some(old), Some(new)) = (self.field_value(field), other.field_value(field)) else {
+                continue;
+            };
+            let equal = old
+                .components
+                .iter()
+                .zip(&new.components)
+                .all(|(a, b)| options.scalar_eq(a, b));
+            if !equal {
+                changes.push(ValueChange {
+                    path: path.clone(),
+                    old,
+                    new,
+                });
+            }
+        }
+        changes
+    }
+}
+
+/// Writes the changes as a pair of lines each, `-path: old` then `+path: new`.
+pub fn write_value_changes(out: &mut impl Write, changes: &[ValueChange]) -> io::Result<()> {
+    for change in changes {
+        writeln!(out, "-{}: {}", change.path, change.old)?;
+        writeln!(out, "+{}: {}", change.path, change.new)?;
+    }
+    Ok(())
+}
+
+/// Prints `write_value_changes` to the terminal colored like `diff_display`, the old value in red and the new one in
+/// green. Prints without color if stdout isn't a terminal.
+pub fn diff_values_display(changes: &[ValueChange]) -> io::Result<()> {
+    let Some(mut t) = term::stdout() else {
+        return write_value_changes(&mut io::stdout().lock(), changes);
+    };
+    for change in changes {
+        t.fg(term::color::RED)?;
+        writeln!(t, "-{}: {}", change.path, change.old)?;
+        t.fg(term::color::GREEN)?;
+        writeln!(t, "+{}: {}", change.path, change.new)?;
+    }
+    t.reset()?;
+    t.flush()
+}
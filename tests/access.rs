@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+
+    use bytemuck::{Pod, Zeroable};
+    use dyn_pod_struct::{
+        access::AccessError, base_type::BaseType, dyn_layout::HasDynLayout, dyn_struct::DynStruct,
+        tracked_dyn_struct::TrackedDynStruct,
+    };
+    use dyn_pod_struct_derive::DynLayout;
+    use glam::{vec3, Vec3};
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Transform {
+        pub position: Vec3,
+        pub scale: f32,
+    }
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Object {
+        pub transform: Transform,
+        pub id: u32,
+        pub visible: u32,
+    }
+
+    #[test]
+    fn test_try_get() {
+        let layout = Object::dyn_layout();
+        let mut dyn_struct = DynStruct::new(
+            &Object {
+                id: 7,
+                ..Default::default()
+            },
+            &layout,
+        );
+
+        assert_eq!(dyn_struct.try_get::<u32>(&["id"]), Ok(&7));
+        *dyn_struct
+            .try_get_mut::<Vec3>(&["transform", "position"])
+            .unwrap() = vec3(1.0, 2.0, 3.0);
+        assert_eq!(
+            dyn_struct.get::<Vec3>(&["transform", "position"]),
+            Some(&vec3(1.0, 2.0, 3.0))
+        );
+
+        assert_eq!(dyn_struct.try_get::<u32>(&[]), Err(AccessError::EmptyPath));
+        assert_eq!(dyn_struct.get::<u32>(&[]), None);
+
+        let err = dyn_struct
+            .try_get::<f32>(&["transform", "rotation"])
+            .unwrap_err();
+        assert_eq!(
+            err,
+            AccessError::PathNotFound {
+                parent: "transform".to_string(),
+                segment: "rotation".to_string(),
+            }
+        );
+        assert_eq!(err.to_string(), "no field `rotation` in `transform`");
+        assert_eq!(
+            dyn_struct.try_get::<f32>(&["id", "x"]).unwrap_err(),
+            AccessError::PathNotFound {
+                parent: "id".to_string(),
+                segment: "x".to_string(),
+            }
+        );
+
+        let err = dyn_struct.try_get::<Vec3>(&["id"]).unwrap_err();
+        assert_eq!(
+            err,
+            AccessError::TypeMismatch {
+                path: "id".to_string(),
+                expected: BaseType::U32,
+                requested: std::any::type_name::<Vec3>(),
+            }
+        );
+        assert!(err.to_string().starts_with("`id` is a u32 (4 bytes)"));
+        // Same size, different type
+        assert!(matches!(
+            dyn_struct.try_get::<f32>(&["id"]),
+            Err(AccessError::TypeMismatch { .. })
+        ));
+
+        assert_eq!(
+            dyn_struct.try_get_raw::<u64>(dyn_struct.data.len() - 4),
+            Err(AccessError::OutOfBounds {
+                offset: 20,
+                size: 8,
+                len: 24,
+            })
+        );
+        assert_eq!(
+            dyn_struct
+                .try_get_raw::<u32>(usize::MAX)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "4 bytes at offset {} is out of bounds of 24 bytes",
+                usize::MAX
+            )
+        );
+    }
+
+    #[test]
+    fn test_tracked_try_get() {
+        let layout = Object::dyn_layout();
+        let mut tracked = TrackedDynStruct::new(&Object::default(), &layout, 4, false);
+
+        assert!(tracked.try_get_mut::<u64>(&["id"]).is_err());
+        assert!(tracked.try_get_mut::<u32>(&["missing"]).is_err());
+        assert!(!tracked.update_bitmask.any);
+
+        *tracked.try_get_mut::<u32>(&["visible"]).unwrap() = 1;
+        assert_eq!(
+            tracked.update_bitmask.ranges().collect::<Vec<_>>(),
+            vec![5..6]
+        );
+        assert_eq!(tracked.try_get::<u32>(&["visible"]), Ok(&1));
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod tests {
+
+    use std::sync::Arc;
+
+    use bytemuck::{bytes_of, Pod, Zeroable};
+    use dyn_pod_struct::{
+        aligned_bytes::{AlignedBytes, MAX_ALIGN},
+        dyn_layout::{DynLayout, HasDynLayout},
+        dyn_struct::DynStruct,
+        dyn_view::DynView,
+        tracked_dyn_struct::TrackedDynStruct,
+    };
+    use glam::{dvec4, DVec4, Mat4};
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Wide {
+        pub id: u128,
+        pub position: DVec4,
+        pub transform: Mat4,
+        pub flags: u32,
+        pub pad0: u32,
+        pub pad1: u32,
+        pub pad2: u32,
+    }
+
+    fn wide() -> Wide {
+        Wide {
+            id: u128::MAX - 1,
+            position: dvec4(1.0, 2.0, 3.0, 4.0),
+            transform: Mat4::from_scale(glam::Vec3::splat(2.0)),
+            flags: 5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_aligned_storage() {
+        let layout = Wide::dyn_layout();
+        assert_eq!(layout.align(), 16);
+
+        // Copying from a misaligned slice still ends up in aligned storage
+        let mut buffer = vec![0u8; size_of::<Wide>() + 1];
+        buffer[1..].copy_from_slice(bytes_of(&wide()));
+        let dyn_struct = DynStruct::from_bytes(buffer[1..].to_vec(), layout.clone());
+        assert!((dyn_struct.data.as_ptr() as usize).is_multiple_of(MAX_ALIGN));
+        assert_eq!(dyn_struct.get::<u128>(&["id"]), Some(&(u128::MAX - 1)));
+        assert_eq!(
+            dyn_struct.get::<DVec4>(&["position"]),
+            Some(&dvec4(1.0, 2.0, 3.0, 4.0))
+        );
+
+        let tracked = TrackedDynStruct::from_bytes(dyn_struct.data.clone(), layout, 16, false);
+        assert_eq!(tracked.get::<u32>(&["flags"]), Some(&5));
+
+        let mut bytes = AlignedBytes::from(vec![1, 2, 3]);
+        bytes.resize(1);
+        bytes.resize(20);
+        assert_eq!(bytes.len(), 20);
+        assert_eq!(&bytes[..4], &[1, 0, 0, 0]);
+        assert!((bytes.as_ptr() as usize).is_multiple_of(MAX_ALIGN));
+        assert_eq!(bytes.into_vec().len(), 20);
+    }
+
+    #[test]
+    fn test_misaligned_view() {
+        let layout = Wide::dyn_layout();
+        // Try every offset so at least one is misaligned for each field regardless of where the Vec is allocated
+        let mut buffer = vec![0u8; size_of::<Wide>() + MAX_ALIGN];
+        for start in 0..MAX_ALIGN {
+            buffer[start..start + size_of::<Wide>()].copy_from_slice(bytes_of(&wide()));
+            let view = DynView::new(&buffer[start..start + size_of::<Wide>()], &layout);
+            let aligned = (view.data.as_ptr() as usize).is_multiple_of(MAX_ALIGN);
+            assert_eq!(view.is_aligned(), aligned);
+
+            assert_eq!(view.read_unaligned::<u128>(&["id"]), Some(u128::MAX - 1));
+            assert_eq!(
+                view.read_unaligned::<DVec4>(&["position"]),
+                Some(dvec4(1.0, 2.0, 3.0, 4.0))
+            );
+            assert_eq!(
+                view.read_unaligned::<Mat4>(&["transform"]),
+                Some(wide().transform)
+            );
+            assert_eq!(view.read_unaligned::<u32>(&["missing"]), None);
+            assert_eq!(view.value(&["flags"]).unwrap().to_string(), "5");
+
+            // Borrowing only works when the field is aligned
+            let address = view.data.as_ptr() as usize;
+            assert_eq!(
+                view.get::<DVec4>(&["position"]).is_some(),
+                (address + 16).is_multiple_of(align_of::<DVec4>())
+            );
+            if aligned {
+                assert_eq!(view.get::<u128>(&["id"]), Some(&(u128::MAX - 1)));
+            }
+
+            let owned = view.to_dyn_struct();
+            assert_eq!(owned.get::<Mat4>(&["transform"]), Some(&wide().transform));
+            assert_eq!(owned.view().data, view.data);
+        }
+    }
+
+    #[repr(C, align(32))]
+    #[derive(Clone, Copy, Pod, Zeroable)]
+    pub struct Overaligned([u8; 32]);
+
+    #[test]
+    #[should_panic(expected = "only aligned to 16")]
+    fn test_overaligned_struct() {
+        let layout = Arc::new(DynLayout::new("Overaligned", 32, Vec::new()));
+        DynStruct::new(&Overaligned([0; 32]), &layout);
+    }
+}
@@ -0,0 +1,198 @@
+#[cfg(test)]
+mod tests {
+
+    use std::sync::Arc;
+
+    use bytemuck::{Pod, Zeroable};
+    use dyn_pod_struct::{
+        aligned_bytes::AlignedBytes,
+        base_type::{BaseType, LEAF_TYPES},
+        dyn_file::{DynFile, FileError},
+        dyn_layout::{DynLayout, HasDynLayout},
+        dyn_struct::{DynField, DynStruct},
+    };
+    use glam::{vec3, vec4, Vec3, Vec4};
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Material {
+        pub color: Vec4,
+        pub roughness: f32,
+        pub metallic: f32,
+        pub flags: u32,
+        pub layer: i32,
+    }
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Instance {
+        pub position: Vec3,
+        pub id: u32,
+        pub material: Material,
+    }
+
+    fn instances() -> Vec<DynStruct> {
+        let layout = Instance::dyn_layout();
+        (0..4)
+            .map(|i| {
+                let instance = Instance {
+                    position: vec3(i as f32, 0.0, -1.0),
+                    id: i,
+                    material: Material {
+                        color: vec4(1.0, 0.5, 0.25, 1.0),
+                        roughness: 0.1 * i as f32,
+                        layer: -(i as i32),
+                        ..Default::default()
+                    },
+                };
+                DynStruct::new(&instance, &layout)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let instances = instances();
+        let bytes = DynFile::write_structs(&instances).unwrap();
+
+        let file = DynFile::parse(&bytes).unwrap();
+        assert_eq!(file.len(), 4);
+        assert_eq!(*file.layout, *Instance::dyn_layout());
+        assert_eq!(file.expect_layout(&Instance::dyn_layout()), Ok(()));
+        // The data is borrowed from the file bytes
+        assert!(std::ptr::eq(
+            file.data.as_ptr_range().end,
+            bytes.as_ptr_range().end
+        ));
+        for (view, instance) in file.iter().zip(&instances) {
+            assert_eq!(view.data, instance.data.as_slice());
+            assert_eq!(
+                view.read_unaligned::<i32>(&["material", "layer"]),
+                instance.get::<i32>(&["material", "layer"]).copied()
+            );
+        }
+        let loaded = file.to_dyn_structs();
+        assert_eq!(loaded[2].get::<u32>(&["id"]), Some(&2));
+
+        // Aligned file bytes can be borrowed in place
+        let aligned = AlignedBytes::from(bytes.as_slice());
+        let file = DynFile::parse(&aligned).unwrap();
+        assert_eq!(
+            file.get(3).get::<Vec3>(&["position"]),
+            Some(&vec3(3.0, 0.0, -1.0))
+        );
+
+        let single = DynStruct::from_file_bytes(&instances[1].to_file_bytes().unwrap()).unwrap();
+        assert_eq!(single.data, instances[1].data);
+        assert_eq!(
+            DynStruct::from_file_bytes(&bytes).unwrap_err(),
+            FileError::CountMismatch {
+                expected: 1,
+                count: 4
+            }
+        );
+        assert!(matches!(
+            file.expect_layout(&Material::dyn_layout()),
+            Err(FileError::LayoutMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_file_array_layout() {
+        let lights = DynLayout::new_array(BaseType::Vec4, 3, 16, 16);
+        let layout = Arc::new(DynLayout::new(
+            "Lights",
+            64,
+            vec![
+                (
+                    "count".to_string(),
+                    DynField {
+                        offset: 0,
+                        ty: BaseType::U32,
+                    },
+                ),
+                (
+                    "lights".to_string(),
+                    DynField {
+                        offset: 16,
+                        ty: BaseType::Array(Arc::new(lights)),
+                    },
+                ),
+            ],
+        ));
+        let mut dyn_struct = DynStruct::from_bytes(vec![0; 64], layout.clone());
+        *dyn_struct.get_mut::<u32>(&["count"]).unwrap() = 3;
+        *dyn_struct.get_mut::<Vec4>(&["lights", "2"]).unwrap() = Vec4::ONE;
+
+        let loaded = DynStruct::from_file_bytes(&dyn_struct.to_file_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.layout, layout);
+        assert_eq!(loaded.get::<Vec4>(&["lights", "2"]), Some(&Vec4::ONE));
+    }
+
+    #[test]
+    fn test_file_hashes_are_stable() {
+        let layout = Arc::new(DynLayout::new(
+            "Counter",
+            4,
+            vec![(
+                "count".to_string(),
+                DynField {
+                    offset: 0,
+                    ty: BaseType::U32,
+                },
+            )],
+        ));
+        let bytes = DynStruct::from_bytes(7u32.to_le_bytes().to_vec(), layout)
+            .to_file_bytes()
+            .unwrap();
+        // xxh64 of the stored bytes, these must never change for version 1 files
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        assert_eq!(u64_at(16), 0x899a_cfb0_7792_54a3);
+        assert_eq!(u64_at(40), 0xf120_8a82_1212_add6);
+
+        // Type tags are indices into LEAF_TYPES and must not move
+        let tag = |ty: BaseType| LEAF_TYPES.iter().position(|t| *t == ty).unwrap();
+        assert_eq!(tag(BaseType::DAffine3), 33);
+        assert_eq!(tag(BaseType::F16), 34);
+        assert_eq!(tag(BaseType::Snorm16x2), 40);
+        assert_eq!(tag(BaseType::F16x3), 41);
+    }
+
+    #[test]
+    fn test_file_errors() {
+        let bytes = DynFile::write_structs(&instances()).unwrap();
+        let parse = |bytes: &[u8]| DynFile::parse(bytes).map(|file| file.len());
+
+        assert_eq!(parse(b"not a file"), Err(FileError::BadMagic));
+        assert_eq!(
+            parse(&bytes[..20]),
+            Err(FileError::Truncated {
+                expected: 48,
+                len: 20
+            })
+        );
+        let err = parse(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(
+            err,
+            FileError::Truncated {
+                expected: bytes.len(),
+                len: bytes.len() - 1
+            }
+        );
+        assert!(err.to_string().starts_with("file is truncated"));
+
+        let corrupt = |at: usize| {
+            let mut bytes = bytes.clone();
+            bytes[at] ^= 0x10;
+            parse(&bytes)
+        };
+        assert_eq!(corrupt(8), Err(FileError::UnsupportedVersion(17)));
+        assert_eq!(corrupt(10), Err(FileError::EndiannessMismatch));
+        // Layout and data are covered by the checksum
+        assert_eq!(corrupt(50), Err(FileError::ChecksumMismatch));
+        assert_eq!(corrupt(bytes.len() - 3), Err(FileError::ChecksumMismatch));
+        assert_eq!(corrupt(16), Err(FileError::LayoutHashMismatch));
+        // Count that doesn't match the data length
+        assert_eq!(corrupt(12), Err(FileError::CorruptLayout));
+    }
+}
@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod tests {
+
+    use bytemuck::{Pod, Zeroable};
+    use dyn_pod_struct::{
+        dyn_history::DynHistory, dyn_layout::HasDynLayout, dyn_struct::DynStruct,
+        tracked_dyn_struct::TrackedDynStruct,
+    };
+    use dyn_pod_struct_derive::DynLayout;
+    use glam::{vec4, Vec4};
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Material {
+        pub base_color: Vec4,
+        pub emissive: Vec4,
+        pub roughness: f32,
+        pub metallic: f32,
+        pub flags: u32,
+        pub texture_index: u32,
+    }
+
+    fn material(history: &DynHistory<DynStruct>) -> Material {
+        *bytemuck::from_bytes(&history.target().data)
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let layout = Material::dyn_layout();
+        let mut history = DynHistory::new(DynStruct::new(&Material::default(), &layout));
+        assert!(!history.can_undo());
+
+        history.set(&["roughness"], 0.5f32).unwrap();
+        history.begin();
+        history
+            .set(&["base_color"], vec4(1.0, 0.0, 0.0, 1.0))
+            .unwrap();
+        // Nested transactions are part of the outer one
+        history.begin();
+        history.set(&["metallic"], 1.0f32).unwrap();
+        history.commit();
+        history.set(&["roughness"], 0.25f32).unwrap();
+        history.commit();
+        assert!(history.set(&["missing"], 0u32).is_none());
+        // Writing the same value isn't recorded
+        history.set(&["flags"], 0u32).unwrap();
+
+        assert!(history.undo());
+        assert_eq!(
+            material(&history),
+            Material {
+                roughness: 0.5,
+                ..Default::default()
+            }
+        );
+        assert!(history.undo());
+        assert_eq!(material(&history), Material::default());
+        assert!(!history.undo());
+
+        assert!(history.redo());
+        assert!(history.redo());
+        assert!(!history.redo());
+        assert_eq!(material(&history).base_color, vec4(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(material(&history).roughness, 0.25);
+
+        // A new edit clears redo
+        history.undo();
+        history.set(&["flags"], 3u32).unwrap();
+        assert!(!history.can_redo());
+
+        history.begin();
+        history.set(&["texture_index"], 9u32).unwrap();
+        history.rollback();
+        assert_eq!(material(&history).texture_index, 0);
+        assert!(!history.in_transaction());
+        history.undo();
+        history.undo();
+        assert_eq!(material(&history), Material::default());
+    }
+
+    #[test]
+    fn test_nested_rollback() {
+        let layout = Material::dyn_layout();
+        let mut history = DynHistory::new(DynStruct::new(&Material::default(), &layout));
+
+        history.begin();
+        history.set(&["roughness"], 0.5f32).unwrap();
+        history.begin();
+        history.set(&["metallic"], 1.0f32).unwrap();
+        history.set(&["roughness"], 0.75f32).unwrap();
+        // Only reverts the inner level
+        history.rollback();
+        assert!(history.in_transaction());
+        assert_eq!(material(&history).roughness, 0.5);
+        assert_eq!(material(&history).metallic, 0.0);
+        assert_eq!(history.memory(), 8);
+        history.set(&["flags"], 2u32).unwrap();
+        history.commit();
+        assert!(!history.in_transaction());
+
+        assert!(history.undo());
+        assert_eq!(material(&history), Material::default());
+        assert!(!history.can_undo());
+        assert!(history.redo());
+        assert_eq!(
+            material(&history),
+            Material {
+                roughness: 0.5,
+                flags: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_history_memory_cap() {
+        let layout = Material::dyn_layout();
+        // Each f32 edit keeps 8 bytes
+        let mut history =
+            DynHistory::new(DynStruct::new(&Material::default(), &layout)).with_memory_cap(24);
+        for i in 1..=5 {
+            history.set(&["roughness"], i as f32).unwrap();
+        }
+        assert_eq!(history.memory(), 24);
+        assert!(history.undo() && history.undo() && history.undo());
+        assert!(!history.undo());
+        assert_eq!(material(&history).roughness, 2.0);
+    }
+
+    #[test]
+    fn test_history_marks_tracked() {
+        let layout = Material::dyn_layout();
+        let tracked = TrackedDynStruct::new(&Material::default(), &layout, 16, false);
+        let mut history = DynHistory::new(tracked);
+        history.set(&["flags"], 1u32).unwrap();
+        history.target_mut().reset_change_detection();
+
+        history.undo();
+        let changed = |history: &DynHistory<TrackedDynStruct>| {
+            history
+                .target()
+                .changed_fields()
+                .map(|(path, _)| path.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            changed(&history),
+            vec!["roughness", "metallic", "flags", "texture_index"]
+        );
+        history.target_mut().reset_change_detection();
+        history.redo();
+        assert_eq!(changed(&history).len(), 4);
+        assert_eq!(history.target().get::<u32>(&["flags"]), Some(&1));
+    }
+}
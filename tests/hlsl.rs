@@ -81,4 +81,18 @@ mod tests {
         assert_eq!(hlsl_layout, rust_layout);
         println!("{}", hlsl_layout);
     }
+
+    #[test]
+    fn test_scatter_shader_compiles() {
+        let shader = dyn_pod_struct::scatter_shader::ScatterShader::new(16, 64);
+        compile_hlsl(
+            "scatter.hlsl",
+            &shader.hlsl(),
+            "main",
+            "cs_6_5",
+            &vec!["-spirv"],
+            &vec![],
+        )
+        .unwrap();
+    }
 }
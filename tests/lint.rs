@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod tests {
+
+    use dyn_pod_struct::{
+        base_type::BaseType,
+        dyn_layout_builder::DynLayoutBuilder,
+        lint::{LintKind, LintOptions, LintSeverity},
+        packing::PackingRules,
+    };
+
+    #[test]
+    fn test_lint() {
+        let layout = DynLayoutBuilder::new("Lit", PackingRules::ReprC)
+            .with_field("a", BaseType::F32)
+            .with_field("b", BaseType::Vec3)
+            .with_field("c", BaseType::Vec4)
+            .with_field("d", BaseType::F64)
+            .with_field("e", BaseType::Vec3)
+            .build();
+        assert_eq!(layout.padding(), vec![52..64]);
+
+        let lints = layout.lint(&LintOptions {
+            push_constant_limit: Some(32),
+            update_stride: Some(24),
+            ..Default::default()
+        });
+        for lint in &lints {
+            println!("{lint}");
+        }
+        let lints = lints
+            .into_iter()
+            .map(|lint| (lint.severity, lint.path, lint.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lints,
+            vec![
+                (
+                    LintSeverity::Info,
+                    "Lit".into(),
+                    LintKind::WastedPadding {
+                        bytes: 12,
+                        percent: 18.75
+                    }
+                ),
+                (
+                    LintSeverity::Error,
+                    "Lit".into(),
+                    LintKind::PushConstantTooLarge {
+                        size: 64,
+                        limit: 32
+                    }
+                ),
+                (
+                    LintSeverity::Warning,
+                    "Lit".into(),
+                    LintKind::UpdateStride {
+                        update_stride: 24,
+                        size: 64
+                    }
+                ),
+                (LintSeverity::Warning, "b".into(), LintKind::Vec3Member),
+                (LintSeverity::Warning, "d".into(), LintKind::DoublePrecision),
+                (LintSeverity::Warning, "e".into(), LintKind::Vec3Member),
+                (
+                    LintSeverity::Warning,
+                    "e".into(),
+                    LintKind::StraddlesVec4Boundary
+                ),
+            ]
+        );
+
+        // A vec3 with a scalar packed after it is fine
+        let layout = DynLayoutBuilder::new("Packed", PackingRules::Std430)
+            .with_field("position", BaseType::Vec3)
+            .with_field("radius", BaseType::F32)
+            .build();
+        assert!(layout.lint(&LintOptions::default()).is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+#[cfg(test)]
+mod tests {
+
+    use bytemuck::{Pod, Zeroable};
+    use dyn_pod_struct::{
+        base_type::BaseType,
+        dyn_file::DynFile,
+        dyn_layout::{DynLayout, HasDynLayout},
+        dyn_struct::DynStruct,
+        dyn_value::SetValueError,
+        packed::{f16, F16x2, F16x3, F16x4, Packed, Snorm16x2, Unorm8x4},
+        packing::PackingRules,
+        tracked_dyn_struct::TrackedDynStruct,
+    };
+    use glam::{vec2, vec3, vec4, Vec2, Vec3};
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Vertex {
+        pub position: Vec3,
+        pub uv: F16x2,
+        pub color: Unorm8x4,
+        pub normal: Snorm16x2,
+        pub tangent: F16x4,
+        pub depth_bias: f16,
+        pub flags: u16,
+        pub id: u32,
+    }
+
+    fn vertex() -> Vertex {
+        Vertex {
+            position: vec3(1.0, 2.0, 3.0),
+            uv: F16x2::pack(vec2(0.25, 0.75)),
+            color: Unorm8x4::from_bits(0xff00_80ff),
+            normal: Snorm16x2::pack(vec2(-1.0, 0.5)),
+            tangent: F16x4::pack(vec4(1.0, 0.0, 0.0, -1.0)),
+            depth_bias: f16::from_f32(0.5),
+            flags: 3,
+            id: 9,
+        }
+    }
+
+    #[test]
+    fn test_packed_layout() {
+        let layout = Vertex::dyn_layout();
+        assert_eq!(layout.size, 40);
+        let field = |name: &str| layout.get_path(&[name]).unwrap();
+        assert_eq!(field("uv").ty, BaseType::F16x2);
+        assert_eq!(field("color").ty, BaseType::Unorm8x4);
+        assert_eq!(field("color").offset, 16);
+        assert_eq!(field("normal").ty, BaseType::Snorm16x2);
+        assert_eq!(field("depth_bias").ty, BaseType::F16);
+        assert_eq!(field("depth_bias").offset, 32);
+
+        // Packed normalized types are aligned like a u32, not like their components
+        assert_eq!(PackingRules::Scalar.align_of(&BaseType::Unorm8x4), 4);
+        assert_eq!(PackingRules::Std430.align_of(&BaseType::F16x4), 8);
+        // The rust types are aligned like their shader counterparts, so derived layouts validate
+        assert_eq!(field("tangent").offset, 24);
+        assert_eq!(align_of::<F16x2>(), 4);
+        assert_eq!(align_of::<F16x4>(), 8);
+        assert!(layout.validate(PackingRules::Std430).is_empty());
+
+        // The new types survive the file format
+        let dyn_struct = DynStruct::new(&vertex(), &layout);
+        let loaded = DynStruct::from_file_bytes(&dyn_struct.to_file_bytes().unwrap()).unwrap();
+        assert_eq!(*loaded.layout, *layout);
+        let file = DynFile::write_structs(&[dyn_struct]).unwrap();
+        assert_eq!(
+            DynFile::parse(&file)
+                .unwrap()
+                .get(0)
+                .read_unpacked::<Unorm8x4>(&["color"]),
+            Some(vec4(1.0, 128.0 / 255.0, 0.0, 1.0))
+        );
+
+        let schema =
+            "struct Vertex { depth: f16, color: Unorm8x4, uv: F16x2, normal: F16x3, w: f16 }";
+        let parsed = DynLayout::parse_schema(schema, PackingRules::Std430).unwrap();
+        assert_eq!(parsed.get_path(&["color"]).unwrap().offset, 4);
+        // Like vec3, f16vec3 is aligned like f16vec4 and a scalar packs after it
+        assert_eq!(parsed.get_path(&["normal"]).unwrap().offset, 16);
+        assert_eq!(parsed.get_path(&["w"]).unwrap().offset, 22);
+        assert_eq!(
+            F16x3::pack(vec3(0.5, -2.0, 8.0)).unpack(),
+            vec3(0.5, -2.0, 8.0)
+        );
+    }
+
+    #[test]
+    fn test_unpacked_accessors() {
+        let layout = Vertex::dyn_layout();
+        let mut dyn_struct = DynStruct::new(&vertex(), &layout);
+
+        assert_eq!(
+            dyn_struct.get_unpacked::<Unorm8x4>(&["color"]),
+            Some(vec4(1.0, 128.0 / 255.0, 0.0, 1.0))
+        );
+        assert_eq!(
+            dyn_struct.get_unpacked::<Snorm16x2>(&["normal"]),
+            Some(vec2(-1.0, 16384.0 / 32767.0))
+        );
+        assert_eq!(dyn_struct.get_unpacked::<f16>(&["depth_bias"]), Some(0.5));
+        assert_eq!(
+            dyn_struct.get_unpacked::<F16x2>(&["uv"]),
+            Some(vec2(0.25, 0.75))
+        );
+        // Same size, different type
+        assert_eq!(dyn_struct.get_unpacked::<Snorm16x2>(&["color"]), None);
+        assert_eq!(dyn_struct.get_unpacked::<Unorm8x4>(&["missing"]), None);
+
+        // Values are clamped and rounded to the nearest step
+        dyn_struct.set_unpacked::<Unorm8x4>(&["color"], vec4(2.0, 0.5, -1.0, 0.1));
+        assert_eq!(
+            dyn_struct.get::<Unorm8x4>(&["color"]),
+            Some(&Unorm8x4([255, 128, 0, 26]))
+        );
+        assert_eq!(
+            dyn_struct.get::<Unorm8x4>(&["color"]).unwrap().to_bits(),
+            0x1a00_80ff
+        );
+        assert_eq!(
+            dyn_struct.set_unpacked::<F16x2>(&["normal"], Vec2::ZERO),
+            None
+        );
+
+        // The most negative value also decodes to -1
+        assert_eq!(Snorm16x2([i16::MIN, -i16::MAX]).unpack(), vec2(-1.0, -1.0));
+
+        let mut tracked = TrackedDynStruct::new(&vertex(), &layout, 4, false);
+        assert_eq!(
+            tracked.set_unpacked::<Snorm16x2>(&["normal"], vec2(-1.0, 0.50001)),
+            Some(false)
+        );
+        assert_eq!(
+            tracked.set_unpacked::<f16>(&["depth_bias"], 0.25),
+            Some(true)
+        );
+        // flags shares the 4 byte update stride block
+        assert_eq!(
+            tracked
+                .changed_fields()
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>(),
+            ["depth_bias", "flags"]
+        );
+    }
+
+    #[test]
+    fn test_packed_values() {
+        let layout = Vertex::dyn_layout();
+        let mut dyn_struct = DynStruct::new(&vertex(), &layout);
+
+        assert_eq!(
+            dyn_struct.get_as_string(&["color"]).unwrap(),
+            "(1, 0.5019608, 0, 1)"
+        );
+        assert_eq!(dyn_struct.get_as_string(&["uv"]).unwrap(), "(0.25, 0.75)");
+        assert_eq!(dyn_struct.get_as_string(&["depth_bias"]).unwrap(), "0.5");
+
+        dyn_struct
+            .set_from_str(&["color"], "(0, 1, 0.5, 1)")
+            .unwrap();
+        assert_eq!(
+            dyn_struct.get::<Unorm8x4>(&["color"]),
+            Some(&Unorm8x4([0, 255, 128, 255]))
+        );
+        dyn_struct.set_from_str(&["normal"], "-0.5, 1").unwrap();
+        assert_eq!(
+            dyn_struct.get::<Snorm16x2>(&["normal"]),
+            Some(&Snorm16x2([-16384, 32767]))
+        );
+        dyn_struct.set_from_str(&["tangent"], "0, 1, 0, 1").unwrap();
+        assert_eq!(
+            dyn_struct.get_unpacked::<F16x4>(&["tangent"]),
+            Some(vec4(0.0, 1.0, 0.0, 1.0))
+        );
+
+        let mut error = |path: &str, s: &str| match dyn_struct.set_from_str(&[path], s) {
+            Err(SetValueError::Parse { reason, .. }) => reason,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(error("color", "0, 1.5, 0, 1"), "1.5 is out of range");
+        assert_eq!(error("normal", "-2, 0"), "-2 is out of range");
+        // f16 tops out at 65504
+        assert_eq!(error("depth_bias", "70000"), "70000 is out of range");
+
+        // Blending decodes normalized components to floats
+        let a = DynStruct::new(&vertex(), &layout);
+        let mut b = a.clone();
+        b.set_unpacked::<Unorm8x4>(&["color"], vec4(0.0, 0.0, 1.0, 1.0));
+        let mid = DynStruct::lerp(&a, &b, 0.5);
+        assert_eq!(
+            mid.get::<Unorm8x4>(&["color"]),
+            Some(&Unorm8x4([128, 64, 128, 255]))
+        );
+    }
+
+    #[cfg(feature = "bevy_reflect")]
+    #[test]
+    fn test_reflect_packed() {
+        use bevy_reflect::Struct;
+
+        let layout = Vertex::dyn_layout();
+        let tracked = TrackedDynStruct::new(&vertex(), &layout, 4, false);
+        let color = tracked
+            .field("color")
+            .unwrap()
+            .try_downcast_ref::<Unorm8x4>();
+        assert_eq!(color, Some(&Unorm8x4::from_bits(0xff00_80ff)));
+        assert!(tracked
+            .field("tangent")
+            .unwrap()
+            .try_downcast_ref::<F16x4>()
+            .is_some());
+        // half::f16 isn't reflectable
+        assert!(tracked.field("depth_bias").is_none());
+    }
+}
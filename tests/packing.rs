@@ -0,0 +1,289 @@
+#[cfg(test)]
+mod tests {
+
+    use bytemuck::Zeroable;
+    use dyn_pod_struct::{
+        base_type::BaseType,
+        dyn_layout::{DynLayout, HasDynLayout},
+        dyn_layout_builder::DynLayoutBuilder,
+        dyn_struct::DynStruct,
+        packing::PackingRules,
+        validate::{LayoutViolation, ViolationKind},
+    };
+    use glam::{vec2, vec3, vec4, Mat4, Vec2, Vec3, Vec4};
+
+    #[repr(C)]
+    #[derive(DynLayout, Copy, Clone, Default, Zeroable, Debug, PartialEq)]
+    pub struct NestedStruct {
+        pub a: Vec3,
+        pub b: f32,
+        pub c: Vec4,
+    }
+
+    #[repr(C)]
+    #[derive(DynLayout, Copy, Clone, Default, Zeroable, Debug, PartialEq)]
+    pub struct InstanceData {
+        pub local_to_world: Mat4,
+        pub aabb_min: Vec3,
+        pub material_index: u32,
+        pub nested: NestedStruct,
+        pub index_count: u32,
+        pub first_index: u32,
+        pub vertex_count: u32,
+        pub first_vertex: u32,
+    }
+
+    fn offset(layout: &DynLayout, path: &[&str]) -> u32 {
+        layout.get_path(path).unwrap().offset
+    }
+
+    #[test]
+    fn test_repr_c_matches_derive() {
+        let nested = DynLayoutBuilder::new("NestedStruct", PackingRules::ReprC)
+            .with_field("a", BaseType::Vec3)
+            .with_field("b", BaseType::F32)
+            .with_field("c", BaseType::Vec4)
+            .build();
+        let layout = DynLayoutBuilder::new("InstanceData", PackingRules::ReprC)
+            .with_field("local_to_world", BaseType::Mat4)
+            .with_field("aabb_min", BaseType::Vec3)
+            .with_field("material_index", BaseType::U32)
+            .with_field("nested", BaseType::Struct(nested))
+            .with_field("index_count", BaseType::U32)
+            .with_field("first_index", BaseType::U32)
+            .with_field("vertex_count", BaseType::U32)
+            .with_field("first_vertex", BaseType::U32)
+            .build();
+        assert_eq!(layout, InstanceData::dyn_layout());
+        assert_eq!(layout.size, size_of::<InstanceData>());
+    }
+
+    #[test]
+    fn test_std430_vec3_padding() {
+        let builder = DynLayoutBuilder::new("Material", PackingRules::Std430)
+            .with_field("roughness", BaseType::F32)
+            .with_field("albedo", BaseType::Vec3)
+            .with_field("metallic", BaseType::F32)
+            .with_field("uv_scale", BaseType::Vec2);
+        let layout = builder.build();
+        assert_eq!(offset(&layout, &["albedo"]), 16);
+        assert_eq!(offset(&layout, &["metallic"]), 28);
+        assert_eq!(offset(&layout, &["uv_scale"]), 32);
+        assert_eq!(layout.size, 48);
+        assert_eq!(builder.padding(), vec![4..16, 40..48]);
+
+        let layout = DynLayoutBuilder::new("Material", PackingRules::Scalar)
+            .with_field("roughness", BaseType::F32)
+            .with_field("albedo", BaseType::Vec3)
+            .build();
+        assert_eq!(offset(&layout, &["albedo"]), 4);
+        assert_eq!(layout.size, 16);
+    }
+
+    #[test]
+    fn test_std140_nested_struct() {
+        let inner = DynLayoutBuilder::new("Inner", PackingRules::Std140)
+            .with_field("a", BaseType::F32)
+            .with_field("b", BaseType::Vec2)
+            .build();
+        assert_eq!(inner.size, 16);
+        let middle = DynLayoutBuilder::new("Middle", PackingRules::Std140)
+            .with_field("x", BaseType::F32)
+            .with_field("inner", BaseType::Struct(inner))
+            .build();
+        let builder = DynLayoutBuilder::new("Outer", PackingRules::Std140)
+            .with_field("y", BaseType::U32)
+            .with_field("middle", BaseType::Struct(middle))
+            .with_field("z", BaseType::F32);
+        let layout = builder.build();
+
+        assert_eq!(offset(&layout, &["middle"]), 16);
+        assert_eq!(offset(&layout, &["middle", "x"]), 16);
+        assert_eq!(offset(&layout, &["middle", "inner"]), 32);
+        assert_eq!(offset(&layout, &["middle", "inner", "a"]), 32);
+        assert_eq!(offset(&layout, &["middle", "inner", "b"]), 40);
+        assert_eq!(offset(&layout, &["z"]), 48);
+        assert_eq!(layout.size, 64);
+        assert_eq!(builder.padding(), vec![4..16, 52..64]);
+    }
+
+    #[test]
+    fn test_hlsl_cbuffer_straddle() {
+        let layout = DynLayoutBuilder::new("Constants", PackingRules::HlslCbuffer)
+            .with_field("a", BaseType::F32)
+            .with_field("b", BaseType::F32)
+            .with_field("c", BaseType::Vec3)
+            .with_field("d", BaseType::F32)
+            .with_field("e", BaseType::Vec2)
+            .build();
+        assert_eq!(offset(&layout, &["c"]), 16);
+        assert_eq!(offset(&layout, &["d"]), 28);
+        assert_eq!(offset(&layout, &["e"]), 32);
+        assert_eq!(layout.size, 48);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_std140_mat3_unrepresentable() {
+        DynLayoutBuilder::new("Bad", PackingRules::Std140).add_field("m", BaseType::Mat3);
+    }
+
+    #[test]
+    fn test_validate_vec3_then_vec4() {
+        // append_type doesn't add any padding
+        let mut layout = DynLayout::default();
+        layout.append_type("a", BaseType::Vec3);
+        layout.append_type("b", BaseType::Vec4);
+        assert_eq!(
+            layout.validate(PackingRules::Std430),
+            vec![LayoutViolation {
+                path: "b".into(),
+                offset: 12,
+                kind: ViolationKind::Misaligned { align: 16 },
+            }]
+        );
+        assert!(layout.validate(PackingRules::Scalar).is_empty());
+
+        assert!(InstanceData::dyn_layout()
+            .validate(PackingRules::Std430)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_validate_nested_struct_and_array() {
+        let inner = DynLayoutBuilder::new("Inner", PackingRules::ReprC)
+            .with_field("x", BaseType::F32)
+            .build();
+        let layout = DynLayoutBuilder::new("Outer", PackingRules::ReprC)
+            .with_field("a", BaseType::F32)
+            .with_field("inner", BaseType::Struct(inner))
+            .with_field("b", BaseType::F32)
+            .with_field("m", BaseType::Mat3)
+            .build();
+        let kinds = layout
+            .validate(PackingRules::Std140)
+            .into_iter()
+            .map(|v| (v.path, v.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                ("inner".into(), ViolationKind::Misaligned { align: 16 }),
+                (
+                    "b".into(),
+                    ViolationKind::Overlap {
+                        previous: "inner".into(),
+                        end: 20
+                    }
+                ),
+                ("m".into(), ViolationKind::Misaligned { align: 16 }),
+                (
+                    "m".into(),
+                    ViolationKind::MatrixStride {
+                        expected: 16,
+                        actual: 12
+                    }
+                ),
+            ]
+        );
+        assert!(layout.validate(PackingRules::Scalar).is_empty());
+
+        // Storage buffer layout is fine as std430 but not as a std140 uniform buffer
+        let layout = DynLayoutBuilder::new("Weights", PackingRules::Std430)
+            .with_array("weights", BaseType::F32, 4)
+            .with_field("count", BaseType::U32)
+            .build();
+        assert_eq!(offset(&layout, &["weights", "3"]), 12);
+        assert!(layout.validate(PackingRules::Std430).is_empty());
+        assert_eq!(
+            layout.validate(PackingRules::Std140)[0].kind,
+            ViolationKind::ArrayStride {
+                expected: 16,
+                actual: 4
+            }
+        );
+
+        // Arrays aren't reflected as fields yet
+        #[cfg(feature = "bevy_reflect")]
+        {
+            use bevy_reflect::Struct;
+            use dyn_pod_struct::tracked_dyn_struct::TrackedDynStruct;
+
+            let tracked = TrackedDynStruct::from_bytes(vec![0u8; layout.size], layout, 4, false);
+            assert!(tracked.field("weights").is_none());
+            assert!(tracked.field("count").is_some());
+        }
+    }
+
+    #[test]
+    fn test_optimize_field_order() {
+        let layout = DynLayoutBuilder::new("Unordered", PackingRules::Std430)
+            .with_field("a", BaseType::F32)
+            .with_field("b", BaseType::Vec4)
+            .with_field("c", BaseType::F32)
+            .with_field("d", BaseType::Vec3)
+            .with_field("e", BaseType::F32)
+            .with_field("f", BaseType::Vec2)
+            .build();
+        assert_eq!(layout.size, 80);
+
+        let reordered = layout.optimize_field_order(PackingRules::Std430);
+        assert_eq!(reordered.layout.size, 48);
+        assert_eq!(reordered.original_size, 80);
+        assert_eq!(reordered.bytes_saved(), 32);
+        assert_eq!(reordered.remap, vec![1, 3, 0, 5, 2, 4]);
+        assert!(reordered.layout.validate(PackingRules::Std430).is_empty());
+
+        let mut original = DynStruct::from_bytes(vec![0; layout.size], layout.clone());
+        *original.get_mut::<f32>(&["a"]).unwrap() = 1.0;
+        *original.get_mut::<Vec4>(&["b"]).unwrap() = vec4(2.0, 3.0, 4.0, 5.0);
+        *original.get_mut::<f32>(&["c"]).unwrap() = 6.0;
+        *original.get_mut::<Vec3>(&["d"]).unwrap() = vec3(7.0, 8.0, 9.0);
+        *original.get_mut::<f32>(&["e"]).unwrap() = 10.0;
+        *original.get_mut::<Vec2>(&["f"]).unwrap() = vec2(11.0, 12.0);
+        let remapped = reordered.remap_data(&original);
+        for (name, field) in &layout.fields {
+            let size = field.ty.size_of();
+            let old = field.offset as usize;
+            let new = remapped.layout.get_path(&[name]).unwrap().offset as usize;
+            assert_eq!(
+                original.data[old..old + size],
+                remapped.data[new..new + size]
+            );
+        }
+
+        // Already optimal layouts keep their order
+        let reordered = InstanceData::dyn_layout().optimize_field_order(PackingRules::ReprC);
+        assert_eq!(reordered.bytes_saved(), 0);
+        assert_eq!(reordered.remap, (0..8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_byte_map() {
+        let layout = DynLayoutBuilder::new("Straddle", PackingRules::ReprC)
+            .with_field("a", BaseType::F32)
+            .with_field("b", BaseType::Vec2)
+            .with_field("c", BaseType::Vec3)
+            .with_field("d", BaseType::U8)
+            .build();
+        let map = layout.byte_map().to_string();
+        println!("{map}");
+        println!("{}", layout.byte_map().ansi(true));
+        assert_eq!(
+            map,
+            "\
+Straddle (28 bytes)
+offset  0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15
+     0  A  A  A  A  B  B  B  B  B  B  B  B  C  C  C  C
+    16  C  C  C  C  C  C  C  C  D  _  _  _
+
+  A  a: f32 (0..4)
+  B  b: Vec2 (4..12)
+  C  c: Vec3 (12..24) ! straddles 16 byte boundary
+  D  d: u8 (24..25)
+  .  padding
+  _  trailing padding
+     3 bytes of padding"
+        );
+    }
+}
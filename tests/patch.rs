@@ -0,0 +1,181 @@
+#[cfg(test)]
+mod tests {
+
+    use bytemuck::{Pod, Zeroable};
+    use dyn_pod_struct::{
+        base_type::BaseType,
+        dyn_layout::HasDynLayout,
+        dyn_patch::{DynPatch, PatchEncoding, PatchError, PatchRange},
+        dyn_struct::DynStruct,
+        tracked_dyn_struct::TrackedDynStruct,
+    };
+    use dyn_pod_struct_derive::DynLayout;
+    use glam::{vec3, Mat4, Vec3};
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct InstanceData {
+        pub local_to_world: Mat4,
+        pub aabb_min: Vec3,
+        pub material_index: u32,
+        pub aabb_max: Vec3,
+        pub bindpose_start: u32,
+    }
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct OtherData {
+        pub local_to_world: Mat4,
+        pub aabb_min: Vec3,
+        pub material: u32,
+        pub aabb_max: Vec3,
+        pub bindpose_start: u32,
+    }
+
+    #[test]
+    fn test_patch_round_trip() {
+        let layout = InstanceData::dyn_layout();
+        let mut instance = TrackedDynStruct::new(&InstanceData::default(), &layout, 4, false);
+        let mut remote = instance.dyn_struct.clone();
+        let previous = instance.dyn_struct.data.clone();
+
+        *instance.get_mut::<Mat4>(&["local_to_world"]).unwrap() =
+            Mat4::from_translation(vec3(1.0, 2.0, 3.0));
+        *instance.get_mut::<u32>(&["bindpose_start"]).unwrap() = 300;
+        let patch = DynPatch::from_changes(&instance);
+        assert_eq!(patch.ranges.len(), 2);
+        assert_eq!(
+            (patch.ranges[0].offset, patch.ranges[0].data.len()),
+            (0, 64)
+        );
+        assert_eq!(
+            (patch.ranges[1].offset, patch.ranges[1].data.len()),
+            (92, 4)
+        );
+        assert_eq!(
+            DynPatch::diff(&remote, &instance.dyn_struct).ranges.len(),
+            // The translation and bindpose_start bytes that differ
+            4
+        );
+
+        let mut sizes = Vec::new();
+        for xor in [false, true] {
+            for rle in [false, true] {
+                let encoding = PatchEncoding { xor, rle };
+                let bytes = patch.encode(encoding, Some(&previous)).unwrap();
+                sizes.push(bytes.len());
+                let decoded = DynPatch::decode(&bytes, Some(&previous)).unwrap();
+                assert_eq!(decoded, patch);
+                // Re-encoding is byte exact
+                assert_eq!(decoded.encode(encoding, Some(&previous)).unwrap(), bytes);
+            }
+        }
+        // Unchanged bytes XOR to zeros which run length encode well
+        assert!(sizes[3] < sizes[1] && sizes[1] < sizes[0]);
+
+        remote.apply_patch(&patch).unwrap();
+        assert_eq!(remote.data, instance.dyn_struct.data);
+
+        let mut tracked_remote = TrackedDynStruct::new(&InstanceData::default(), &layout, 4, false);
+        tracked_remote.apply_patch(&patch).unwrap();
+        assert_eq!(
+            tracked_remote.update_bitmask.ranges().collect::<Vec<_>>(),
+            instance.update_bitmask.ranges().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_patch_errors() {
+        let layout = InstanceData::dyn_layout();
+        let instance = DynStruct::new(&InstanceData::default(), &layout);
+        let mut changed = instance.clone();
+        *changed.get_mut::<Vec3>(&["aabb_max"]).unwrap() = vec3(1.0, 1.0, 1.0);
+        let patch = DynPatch::diff(&instance, &changed);
+
+        let mut other = DynStruct::new(&OtherData::default(), &OtherData::dyn_layout());
+        assert!(matches!(
+            other.apply_patch(&patch),
+            Err(PatchError::LayoutMismatch { .. })
+        ));
+        assert_eq!(other.data, instance.data);
+
+        let encoding = PatchEncoding {
+            xor: true,
+            rle: true,
+        };
+        let bytes = patch.encode(encoding, Some(&instance.data)).unwrap();
+        assert_eq!(DynPatch::decode(&bytes, None), Err(PatchError::MissingBase));
+        assert_eq!(
+            DynPatch::decode(&bytes[..bytes.len() - 1], Some(&instance.data)),
+            Err(PatchError::Corrupt)
+        );
+
+        let mut too_big = patch.clone();
+        too_big.ranges[0].offset = 95;
+        let mut copy = instance.clone();
+        assert_eq!(
+            copy.apply_patch(&too_big),
+            Err(PatchError::OutOfBounds {
+                offset: 95,
+                len: too_big.ranges[0].data.len(),
+                size: 96
+            })
+        );
+        assert!(matches!(
+            too_big.encode(encoding, Some(&instance.data)),
+            Err(PatchError::OutOfBounds { offset: 95, .. })
+        ));
+
+        // Ranges must be sorted and not overlap
+        let mut unordered = patch.clone();
+        unordered.ranges.push(PatchRange {
+            offset: 4,
+            data: vec![1; 4],
+        });
+        assert_eq!(
+            unordered.encode(PatchEncoding::default(), None),
+            Err(PatchError::UnorderedRange { offset: 4 })
+        );
+        let mut overlapping = patch.clone();
+        let range = overlapping.ranges[0].clone();
+        overlapping.ranges.push(PatchRange {
+            offset: range.offset + 1,
+            data: range.data.clone(),
+        });
+        assert!(overlapping.encode(encoding, Some(&instance.data)).is_err());
+        assert!(matches!(
+            copy.apply_patch(&overlapping),
+            Err(PatchError::UnorderedRange { .. })
+        ));
+        assert_eq!(copy.data, instance.data);
+
+        // An offset near usize::MAX must not wrap around the bounds check
+        let mut overflowing = patch.clone();
+        overflowing.ranges = vec![PatchRange {
+            offset: usize::MAX - 1,
+            data: vec![1; 4],
+        }];
+        assert!(matches!(
+            copy.apply_patch(&overflowing),
+            Err(PatchError::OutOfBounds { .. })
+        ));
+
+        assert_eq!(patch.encode(encoding, None), Err(PatchError::MissingBase));
+    }
+
+    #[test]
+    fn test_layout_hash_is_stable() {
+        // xxh64 of the layout encoding, shared with the file header. Must not change between builds.
+        let layout = OtherData::dyn_layout();
+        assert_eq!(layout.layout_hash(), 0x3d72_ae5b_eaad_ec9d);
+
+        // The cached hash is cleared when the layout changes
+        let mut extended = (*layout).clone();
+        extended.append_type("extra", BaseType::U32);
+        assert_ne!(extended.layout_hash(), layout.layout_hash());
+        extended.name = "Renamed".to_string();
+        let stale = extended.layout_hash();
+        extended.invalidate_cache();
+        assert_ne!(extended.layout_hash(), stale);
+    }
+}
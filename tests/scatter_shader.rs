@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod tests {
+
+    use bytemuck::{Pod, Zeroable};
+    use dyn_pod_struct::{
+        dyn_layout::HasDynLayout, scatter_shader::ScatterShader,
+        tracked_dyn_struct::TrackedDynStruct, upload_batch::RetainedUploadBatch,
+    };
+    use dyn_pod_struct_derive::DynLayout;
+    use glam::{vec3, Mat4, Vec3};
+    use naga::{
+        front::wgsl,
+        valid::{Capabilities, ValidationFlags, Validator},
+    };
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct InstanceData {
+        pub local_to_world: Mat4,
+        pub aabb_min: Vec3,
+        pub material_index: u32,
+        pub aabb_max: Vec3,
+        pub bindpose_start: u32,
+    }
+
+    #[test]
+    fn test_scatter_wgsl_validates() {
+        for update_stride in [4, 16, 64] {
+            for workgroup_size in [1, 64, 256] {
+                let shader = ScatterShader::new(update_stride, workgroup_size);
+                let module = wgsl::parse_str(&shader.wgsl()).unwrap();
+                let mut validator = Validator::new(ValidationFlags::all(), Capabilities::empty());
+                validator.validate(&module).unwrap();
+
+                let entry_point = &module.entry_points[0];
+                assert_eq!(entry_point.name, "main");
+                assert_eq!(entry_point.workgroup_size, [workgroup_size, 1, 1]);
+                let bindings = module
+                    .global_variables
+                    .iter()
+                    .map(|(_, var)| {
+                        let binding = var.binding.as_ref().unwrap();
+                        (var.name.clone().unwrap(), binding.group, binding.binding)
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    bindings,
+                    vec![
+                        ("src".to_string(), 0, 0),
+                        ("update_indices".to_string(), 0, 1),
+                        ("dst".to_string(), 0, 2),
+                        ("config".to_string(), 0, 3),
+                    ]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_scatter_cpu() {
+        let layout = InstanceData::dyn_layout();
+        let size = size_of::<InstanceData>();
+        let mut instances = (0..5)
+            .map(|_| TrackedDynStruct::new(&InstanceData::default(), &layout, 32, true))
+            .collect::<Vec<_>>();
+        *instances[0].get_mut::<u32>(&["material_index"]).unwrap() = 3;
+        *instances[2].get_mut::<Mat4>(&["local_to_world"]).unwrap() =
+            Mat4::from_translation(vec3(1.0, 2.0, 3.0));
+        *instances[4].get_mut::<Vec3>(&["aabb_max"]).unwrap() = vec3(4.0, 5.0, 6.0);
+
+        let mut batch = RetainedUploadBatch::new(32);
+        // Leave a gap in the retained buffer between each struct
+        batch.extend(
+            instances
+                .iter_mut()
+                .enumerate()
+                .map(|(i, instance)| (i * 2 * size, instance)),
+        );
+        assert_eq!(batch.len(), 5 * size / 32);
+
+        let mut expected = vec![0u8; 10 * size];
+        batch.apply(&mut expected);
+        for (i, instance) in instances.iter().enumerate() {
+            assert_eq!(
+                &expected[i * 2 * size..(i * 2 + 1) * size],
+                instance.dyn_struct.data.as_slice()
+            );
+        }
+
+        for workgroup_size in [1, 3, 64] {
+            let shader = ScatterShader::for_batch(&batch, workgroup_size);
+            let mut retained = vec![0u32; 10 * size / 4];
+            shader.scatter_cpu(
+                shader.config(batch.len()),
+                &batch.src,
+                &batch.update_indices,
+                &mut retained,
+            );
+            assert_eq!(
+                bytemuck::cast_slice::<u32, u8>(&retained),
+                expected.as_slice()
+            );
+
+            // A smaller count leaves the last update out, even when its invocation is in a dispatched workgroup
+            let count = batch.len() - 1;
+            assert!(
+                shader.dispatch_count(count) * workgroup_size > count as u32 || workgroup_size == 1
+            );
+            let mut partial = vec![0u32; 10 * size / 4];
+            shader.scatter_cpu(
+                shader.config(count),
+                &batch.src,
+                &batch.update_indices,
+                &mut partial,
+            );
+            let stride = shader.stride_words() as usize;
+            let last = batch.update_indices[count] as usize * stride;
+            retained[last..last + stride].fill(0);
+            assert_eq!(partial, retained);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+#[cfg(test)]
+mod tests {
+
+    use bytemuck::{Pod, Zeroable};
+    use dyn_pod_struct::{
+        base_type::BaseType,
+        dyn_layout::{DynLayout, HasDynLayout},
+        dyn_layout_builder::DynLayoutBuilder,
+        packing::PackingRules,
+        schema::{FromLayoutError, Schema, SchemaError},
+    };
+    use glam::{Vec3, Vec4};
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Surface {
+        pub roughness: f32,
+        pub layer: i32,
+    }
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Preset {
+        pub position: Vec3,
+        pub id: u32,
+        pub surface: Surface,
+        pub scale: f32,
+        pub bias: f32,
+        pub color: Vec4,
+    }
+
+    const MATERIAL: &str = "
+        // Parameter block for the toon material
+        packing std140
+
+        struct Light {
+            position: Vec3,
+            color: Vec4,
+        }
+
+        struct Material {
+            base_color: Vec4,
+            lights: [Light; 4],
+            weights: [[f32; 2]; 2], // std140 rounds the stride up to 16
+            flags: u32
+        }
+    ";
+
+    #[test]
+    fn test_parse_schema() {
+        let schema = Schema::parse(MATERIAL, PackingRules::ReprC).unwrap();
+        assert_eq!(schema.rules, PackingRules::Std140);
+        let light = schema.get("Light").unwrap();
+        assert_eq!(light.size, 32);
+        assert_eq!(light.get_path(&["color"]).unwrap().offset, 16);
+
+        let material = DynLayout::parse_schema(MATERIAL, PackingRules::ReprC).unwrap();
+        assert_eq!(material.name, "Material");
+        assert_eq!(
+            material.get_path(&["lights", "2", "color"]).unwrap().offset,
+            16 + 64 + 16
+        );
+        assert_eq!(
+            material.get_path(&["weights", "1", "1"]).unwrap().offset,
+            144 + 32 + 16
+        );
+        assert_eq!(material.get_path(&["flags"]).unwrap().offset, 208);
+        assert_eq!(material.size, 224);
+
+        // Display writes the schema back
+        let text = schema.to_string();
+        assert!(text.starts_with("packing std140\n\nstruct Light {\n    position: Vec3,\n"));
+        assert!(text.contains("    weights: [[f32; 2]; 2],\n"));
+        assert_eq!(Schema::parse(&text, PackingRules::ReprC).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_schema_from_layout() {
+        let layout = Preset::dyn_layout();
+        let schema = Schema::from_layout(&layout, PackingRules::ReprC).unwrap();
+        assert_eq!(schema.structs.len(), 2);
+        assert_eq!(
+            schema.to_string(),
+            "packing repr_c
+
+struct Surface {
+    roughness: f32,
+    layer: i32,
+}
+
+struct Preset {
+    position: Vec3,
+    id: u32,
+    surface: Surface,
+    scale: f32,
+    bias: f32,
+    color: Vec4,
+}
+"
+        );
+        let parsed = Schema::parse(&schema.to_string(), PackingRules::Std430).unwrap();
+        assert_eq!(parsed, schema);
+        assert_eq!(**parsed.root().unwrap(), *layout);
+
+        // std140 rounds the size of Surface up to 16
+        assert_eq!(
+            Schema::from_layout(&layout, PackingRules::Std140),
+            Err(FromLayoutError::Mismatch {
+                name: "Surface".to_string()
+            })
+        );
+
+        let surface = |ty| {
+            DynLayoutBuilder::new("Surface", PackingRules::ReprC)
+                .with_field("roughness", ty)
+                .build()
+        };
+        let mixed = DynLayoutBuilder::new("Mixed", PackingRules::ReprC)
+            .with_field("a", BaseType::Struct(surface(BaseType::F32)))
+            .with_field("b", BaseType::Struct(surface(BaseType::U32)))
+            .build();
+        assert_eq!(
+            Schema::from_layout(&mixed, PackingRules::ReprC),
+            Err(FromLayoutError::DuplicateName {
+                name: "Surface".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_schema_errors() {
+        let error = |src: &str| Schema::parse(src, PackingRules::Std430).unwrap_err();
+        let at = |line, column, message: &str| SchemaError {
+            line,
+            column,
+            message: message.to_string(),
+        };
+
+        assert_eq!(
+            error("struct A {\n    a: f32,\n    b: Vec5,\n}"),
+            at(3, 8, "unknown type `Vec5`")
+        );
+        assert_eq!(
+            error("struct A {\n    a: f32\n    b: f32,\n}"),
+            at(3, 5, "expected `,` or `}`, found `b`")
+        );
+        assert_eq!(
+            error("struct A { a: f32, a: u32 }"),
+            at(1, 20, "duplicate field `a`")
+        );
+        assert_eq!(
+            error("packing std999"),
+            at(1, 9, "unknown packing rules `std999`")
+        );
+        assert_eq!(
+            error("struct A { a: [f32; 0] }"),
+            at(1, 21, "expected an array length, found `0`")
+        );
+        assert_eq!(
+            error("struct A { a: f32, }\nstruct A {}"),
+            at(2, 8, "`A` is already defined")
+        );
+        assert_eq!(
+            error("struct A { a: f32 $ }"),
+            at(1, 19, "unexpected character `$`")
+        );
+        assert_eq!(
+            error("struct A {\n  a: f32,"),
+            at(2, 10, "expected a field name or `}`, found end of input")
+        );
+        assert_eq!(
+            error("struct A { m: Mat3 }"),
+            at(1, 15, "Mat3 can't be represented with std430 packing")
+        );
+        assert_eq!(
+            error("struct A { m: Mat3 }").to_string(),
+            "1:15: Mat3 can't be represented with std430 packing"
+        );
+        assert_eq!(
+            Schema::parse("struct A { m: [Mat3; 2] }", PackingRules::Std140).unwrap_err(),
+            at(1, 15, "[Mat3; 2] can't be represented with std140 packing")
+        );
+        assert_eq!(
+            DynLayout::parse_schema("// nothing\n", PackingRules::ReprC).unwrap_err(),
+            at(1, 11, "expected at least one struct")
+        );
+        assert!(Schema::parse("struct A { m: Mat3 }", PackingRules::ReprC)
+            .unwrap()
+            .get("A")
+            .is_some_and(|a| a.fields[0].1.ty == BaseType::Mat3));
+    }
+}
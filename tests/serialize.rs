@@ -0,0 +1,184 @@
+#![cfg(feature = "serde")]
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::Arc;
+
+    use bincode::Options;
+    use bytemuck::{Pod, Zeroable};
+    use dyn_pod_struct::{
+        base_type::BaseType,
+        dyn_layout::{DynLayout, HasDynLayout},
+        dyn_struct::{DynField, DynStruct},
+        packing::PackingRules,
+        serde_for_dyn::DynStructSeed,
+    };
+    use glam::{uvec2, vec3, Mat2, UVec2, Vec3};
+    use serde::de::DeserializeSeed;
+    use serde_json::json;
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Surface {
+        pub roughness: f32,
+        pub layer: i32,
+    }
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Preset {
+        pub position: Vec3,
+        pub id: u32,
+        pub tiles: UVec2,
+        pub surface: Surface,
+        pub uv_transform: Mat2,
+    }
+
+    fn preset() -> Preset {
+        Preset {
+            position: vec3(1.0, 0.1, -2.0),
+            id: 42,
+            tiles: uvec2(3, 4),
+            surface: Surface {
+                roughness: 0.5,
+                layer: -1,
+            },
+            uv_transform: Mat2::from_cols_array(&[1.0, 2.0, 3.0, 4.0]),
+        }
+    }
+
+    fn from_json(layout: &Arc<DynLayout>, value: serde_json::Value) -> Result<DynStruct, String> {
+        DynStructSeed(layout)
+            .deserialize(value)
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_serde_layout() {
+        let layout = Preset::dyn_layout();
+        let value = serde_json::to_value(&*layout).unwrap();
+        assert_eq!(value["name"], "Preset");
+        assert_eq!(
+            value["fields"][0],
+            json!({ "name": "position", "offset": 0, "ty": "Vec3" })
+        );
+        assert_eq!(value["fields"][3]["ty"]["Struct"]["name"], "Surface");
+
+        let decoded: DynLayout = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded, *layout);
+
+        let array = DynLayout::new(
+            "Weights",
+            16,
+            vec![(
+                "weights".to_string(),
+                DynField {
+                    offset: 0,
+                    ty: BaseType::Array(Arc::new(DynLayout::new_array(BaseType::F32, 4, 4, 0))),
+                },
+            )],
+        );
+        let json = serde_json::to_string(&array).unwrap();
+        assert_eq!(serde_json::from_str::<DynLayout>(&json).unwrap(), array);
+
+        let mut value = serde_json::to_value(&array).unwrap();
+        value["size"] = json!(8);
+        assert!(serde_json::from_value::<DynLayout>(value).is_err());
+    }
+
+    #[test]
+    fn test_serde_struct() {
+        let layout = Preset::dyn_layout();
+        let dyn_struct = DynStruct::new(&preset(), &layout);
+
+        // to_string writes f32 with f32 precision, to_value would widen 0.1 to f64 first
+        let json = serde_json::to_string(&dyn_struct).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "position": [1.0, 0.1, -2.0],
+                "id": 42,
+                "tiles": [3, 4],
+                "surface": { "roughness": 0.5, "layer": -1 },
+                "uv_transform": [[1.0, 2.0], [3.0, 4.0]],
+            })
+        );
+        let decoded = from_json(&layout, value.clone()).unwrap();
+        assert_eq!(decoded.data, dyn_struct.data);
+
+        // Binary formats store the bytes
+        let bytes = bincode::options().serialize(&dyn_struct).unwrap();
+        assert_eq!(bytes.len(), 1 + layout.size);
+        let decoded = DynStructSeed(&layout)
+            .deserialize(&mut bincode::Deserializer::from_slice(
+                &bytes,
+                bincode::options(),
+            ))
+            .unwrap();
+        assert_eq!(decoded.data, dyn_struct.data);
+        assert!(DynStructSeed(&Surface::dyn_layout())
+            .deserialize(&mut bincode::Deserializer::from_slice(
+                &bytes,
+                bincode::options(),
+            ))
+            .is_err());
+
+        let with = |pointer: &str, new: serde_json::Value| {
+            let mut value = value.clone();
+            *value.pointer_mut(pointer).unwrap() = new;
+            from_json(&layout, value)
+        };
+        assert!(with("/id", json!(-1))
+            .unwrap_err()
+            .contains("32 bit unsigned"));
+        assert!(with("/surface/layer", json!(1.5)).is_err());
+        assert!(with("/tiles", json!([1, 2, 3])).is_err());
+        assert!(with("/uv_transform", json!([[1.0, 2.0]])).is_err());
+        assert_eq!(
+            with("/surface", json!({ "roughness": 0.5 })).unwrap_err(),
+            "missing field `layer` in Surface"
+        );
+        assert!(with(
+            "/surface",
+            json!({ "roughness": 0.5, "layer": 1, "extra": 2 })
+        )
+        .unwrap_err()
+        .starts_with("unknown field `extra`"));
+        // Floats accept integers
+        let decoded = with("/surface/roughness", json!(1)).unwrap();
+        assert_eq!(decoded.get::<f32>(&["surface", "roughness"]), Some(&1.0));
+    }
+
+    #[test]
+    fn test_serde_packed() {
+        let layout = DynLayout::parse_schema(
+            "struct Vertex { color: Unorm8x4, normal: Snorm16x2, depth: f16 }",
+            PackingRules::ReprC,
+        )
+        .unwrap();
+        let value = json!({ "color": [1.0, 0.5, 0.0, 1.0], "normal": [-1.0, 0.0], "depth": 0.25 });
+        let dyn_struct = from_json(&layout, value).unwrap();
+        assert_eq!(&dyn_struct.data[..4], &[255, 128, 0, 255]);
+
+        // Normalized components are written as the floats they decode to
+        let json = serde_json::to_string(&dyn_struct).unwrap();
+        assert_eq!(
+            json,
+            r#"{"color":[1.0,0.5019608,0.0,1.0],"normal":[-1.0,0.0],"depth":0.25}"#
+        );
+        assert_eq!(
+            from_json(&layout, serde_json::from_str(&json).unwrap())
+                .unwrap()
+                .data,
+            dyn_struct.data
+        );
+
+        let error = from_json(
+            &layout,
+            json!({ "color": [2, 0, 0, 1], "normal": [0, 0], "depth": 0 }),
+        );
+        assert!(error.unwrap_err().contains("a number between 0 and 1"));
+    }
+}
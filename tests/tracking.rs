@@ -0,0 +1,324 @@
+#[cfg(test)]
+mod tests {
+
+    use std::ops::Range;
+
+    use bytemuck::{Pod, Zeroable};
+    use dyn_pod_struct::{
+        dyn_layout::HasDynLayout, retrieve_changes_coalesced, tracked_dyn_struct::TrackedDynStruct,
+        update_bitmask::UpdateBitmask, upload_batch::RetainedUploadBatch,
+    };
+    use dyn_pod_struct_derive::DynLayout;
+    use glam::{vec3, Mat4, Vec3};
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct InstanceData {
+        pub local_to_world: Mat4,
+        pub aabb_min: Vec3,
+        pub material_index: u32,
+        pub aabb_max: Vec3,
+        pub bindpose_start: u32,
+    }
+
+    fn changed_paths(instance: &TrackedDynStruct) -> Vec<&str> {
+        instance.changed_fields().map(|(path, _)| path).collect()
+    }
+
+    fn changed_blocks(instance: &TrackedDynStruct) -> Vec<usize> {
+        let blocks = instance.dyn_struct.data.len() / instance.update_stride();
+        (0..blocks)
+            .filter(|i| instance.update_bitmask.get(*i))
+            .collect()
+    }
+
+    #[test]
+    fn test_changed_fields() {
+        let layout = InstanceData::dyn_layout();
+        let data = InstanceData::default();
+
+        let mut instance = TrackedDynStruct::new(&data, &layout, 4, false);
+        assert!(changed_paths(&instance).is_empty());
+        *instance.get_mut::<u32>(&["material_index"]).unwrap() = 7;
+        assert_eq!(changed_paths(&instance), vec!["material_index"]);
+        *instance.get_mut::<Mat4>(&["local_to_world"]).unwrap() = Mat4::IDENTITY;
+        assert_eq!(
+            changed_paths(&instance),
+            vec!["local_to_world", "material_index"]
+        );
+        instance.reset_change_detection();
+        assert!(changed_paths(&instance).is_empty());
+
+        // Fields sharing a stride block with a changed field are included
+        let mut instance = TrackedDynStruct::new(&data, &layout, 16, false);
+        *instance.get_mut::<u32>(&["material_index"]).unwrap() = 7;
+        assert_eq!(changed_paths(&instance), vec!["aabb_min", "material_index"]);
+
+        let instance = TrackedDynStruct::new(&data, &layout, 16, true);
+        assert_eq!(changed_paths(&instance).len(), layout.leaves().len());
+
+        // The cached leaves follow edits to the public fields once invalidated
+        let mut renamed = (*layout).clone();
+        renamed.fields[0].0 = "transform".to_string();
+        assert_eq!(renamed.leaves()[0].0, "local_to_world");
+        renamed.invalidate_cache();
+        assert_eq!(renamed.leaves()[0].0, "transform");
+        assert_ne!(renamed, *layout);
+    }
+
+    #[test]
+    fn test_compare_on_write() {
+        let layout = InstanceData::dyn_layout();
+        let data = InstanceData::default();
+        let mut instance = TrackedDynStruct::new(&data, &layout, 4, false);
+
+        assert_eq!(instance.set(&["material_index"], 0u32), Some(false));
+        assert!(!instance.changed());
+        assert_eq!(instance.set(&["missing"], 0u32), None);
+
+        assert_eq!(instance.set(&["material_index"], 5u32), Some(true));
+        assert_eq!(*instance.get::<u32>(&["material_index"]).unwrap(), 5);
+        assert_eq!(changed_blocks(&instance), vec![19]);
+        instance.reset_change_detection();
+
+        // Only the one changed component of the matrix is marked
+        let mut m = Mat4::IDENTITY;
+        m.z_axis.x = 1.0;
+        assert_eq!(instance.set(&["local_to_world"], m), Some(true));
+        assert_eq!(changed_blocks(&instance), vec![8]);
+        instance.reset_change_detection();
+
+        {
+            let mut aabb_min = instance.get_mut_guarded::<Vec3>(&["aabb_min"]).unwrap();
+            *aabb_min = Vec3::ZERO;
+        }
+        assert!(!instance.changed());
+        {
+            let mut aabb_min = instance.get_mut_guarded::<Vec3>(&["aabb_min"]).unwrap();
+            aabb_min.y = 2.0;
+        }
+        assert_eq!(changed_blocks(&instance), vec![17]);
+        assert_eq!(
+            *instance.get::<Vec3>(&["aabb_min"]).unwrap(),
+            vec3(0.0, 2.0, 0.0)
+        );
+
+        // Larger stride blocks are marked if any byte in them changed
+        let mut instance = TrackedDynStruct::new(&data, &layout, 16, false);
+        assert_eq!(instance.set(&["bindpose_start"], 1u32), Some(true));
+        assert_eq!(changed_blocks(&instance), vec![5]);
+    }
+
+    #[test]
+    fn test_multiple_consumers() {
+        let layout = InstanceData::dyn_layout();
+        let data = InstanceData::default();
+        let mut instance = TrackedDynStruct::new(&data, &layout, 4, false);
+        instance.set_consumer_count(3);
+
+        let retrieve = |instance: &mut TrackedDynStruct, consumer| {
+            let mut ranges = Vec::new();
+            instance.retrieve_changes_for_and_ack::<u32>(consumer, |_, start, end| {
+                ranges.push(start..end)
+            });
+            ranges
+        };
+
+        // New consumers haven't received anything yet
+        for consumer in 0..3 {
+            assert_eq!(retrieve(&mut instance, consumer), vec![0..16, 16..24]);
+        }
+        assert!(!instance.consumer_changed(0));
+
+        // Frame 0
+        instance.set(&["material_index"], 1u32);
+        assert_eq!(retrieve(&mut instance, 0), vec![19..20]);
+        // Consumers don't clear the primary bitmask
+        assert!(instance.changed());
+        assert_eq!(changed_paths(&instance), vec!["material_index"]);
+        instance.reset_change_detection();
+        // Frame 1
+        instance.set(&["bindpose_start"], 2u32);
+        assert_eq!(retrieve(&mut instance, 1), vec![19..20, 23..24]);
+        // Frame 2
+        assert_eq!(retrieve(&mut instance, 2), vec![19..20, 23..24]);
+        // Frame 3, back to the first copy
+        assert_eq!(retrieve(&mut instance, 0), vec![23..24]);
+        assert!(!instance.consumer_changed(1));
+        assert!(!instance.consumer_changed(2));
+        // Frame 1 was never reset
+        assert!(instance.changed());
+    }
+
+    #[test]
+    fn test_bitmask_ranges() {
+        let mut bitmask = UpdateBitmask::new(70, false);
+        assert_eq!(bitmask.ranges().count(), 0);
+
+        bitmask.set(3..40);
+        bitmask.set_one(45);
+        bitmask.set(60..70);
+        assert_eq!(bitmask.count_ones(), 37 + 1 + 10);
+        assert_eq!(
+            bitmask.ranges().collect::<Vec<_>>(),
+            vec![3..40, 45..46, 60..70]
+        );
+        assert!(bitmask.any_in_range(40..46));
+        assert!(!bitmask.any_in_range(40..45));
+
+        bitmask.clear_range(10..33);
+        assert_eq!(
+            bitmask.ranges().collect::<Vec<_>>(),
+            vec![3..10, 33..40, 45..46, 60..70]
+        );
+
+        // Bits past the end of the last word are never set
+        let all = UpdateBitmask::new(70, true);
+        assert_eq!(all.count_ones(), 70);
+        assert_eq!(all.ranges().collect::<Vec<_>>(), vec![0..70]);
+
+        let mut other = UpdateBitmask::new(70, false);
+        other.set(0..36);
+        let mut intersection = bitmask.clone();
+        intersection.intersect_with(&other);
+        assert_eq!(
+            intersection.ranges().collect::<Vec<_>>(),
+            vec![3..10, 33..36]
+        );
+        let mut difference = bitmask.clone();
+        difference.difference_with(&other);
+        assert_eq!(
+            difference.ranges().collect::<Vec<_>>(),
+            vec![36..40, 45..46, 60..70]
+        );
+        let mut union = bitmask.clone();
+        union.union_with(&other);
+        assert_eq!(
+            union.ranges().collect::<Vec<_>>(),
+            vec![0..40, 45..46, 60..70]
+        );
+
+        difference.difference_with(&all);
+        assert!(!difference.any_set());
+        assert_eq!(difference.ranges().count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bitmask_size_mismatch() {
+        // A 17th bit would be dropped, not merged
+        let mut bitmask = UpdateBitmask::new(16, false);
+        bitmask.union_with(&UpdateBitmask::new(17, true));
+    }
+
+    fn coalesced(instance: &TrackedDynStruct, max_gap: usize, min_run: usize) -> Vec<Range<u32>> {
+        let mut ranges = Vec::new();
+        instance.retrieve_changes_coalesced::<u32>(max_gap, min_run, |data, start, end| {
+            assert_eq!(data.len() as u32, end - start);
+            ranges.push(start..end);
+        });
+        ranges
+    }
+
+    #[test]
+    fn test_coalesced_retrieval() {
+        let layout = InstanceData::dyn_layout();
+        let mut instance = TrackedDynStruct::new(&InstanceData::default(), &layout, 4, false);
+
+        instance.mark_changed::<Mat4>(0);
+        instance.mark_changed::<Vec3>(64);
+        instance.mark_changed::<u32>(92);
+        let mut chunked = Vec::new();
+        instance.retrieve_changes::<u32>(|_, start, end| chunked.push(start..end));
+        assert_eq!(chunked, vec![0..16, 16..19, 23..24]);
+        assert_eq!(coalesced(&instance, 0, 0), vec![0..19, 23..24]);
+        assert_eq!(coalesced(&instance, 4, 0), vec![0..24]);
+
+        let mut with_macro = Vec::new();
+        let mut retrieve = || {
+            retrieve_changes_coalesced!(u32, instance, 3, 0, |_, start, end| {
+                with_macro.push(start..end)
+            });
+        };
+        retrieve();
+        assert_eq!(with_macro, vec![0..19, 23..24]);
+
+        instance.reset_change_detection();
+        instance.mark_changed::<u32>(76);
+        assert_eq!(coalesced(&instance, 0, 4), vec![19..23]);
+        // min_run is clamped to the end of the struct
+        instance.reset_change_detection();
+        instance.mark_changed::<u32>(92);
+        assert_eq!(coalesced(&instance, 0, 4), vec![23..24]);
+    }
+
+    #[test]
+    fn test_change_stats() {
+        let layout = InstanceData::dyn_layout();
+        let mut instance = TrackedDynStruct::new(&InstanceData::default(), &layout, 16, false);
+        assert!(instance.stats().is_none());
+        instance.enable_stats();
+
+        *instance.get_mut::<u32>(&["material_index"]).unwrap() = 7;
+        instance.reset_change_detection();
+        let frame = instance.last_frame_stats().unwrap();
+        assert_eq!((frame.bytes_marked, frame.blocks_marked), (4, 1));
+        assert_eq!((frame.bytes_uploaded, frame.bytes_changed), (16, 1));
+
+        // Writing the same value is marked and uploaded but doesn't change anything
+        *instance.get_mut::<u32>(&["material_index"]).unwrap() = 7;
+        *instance.get_mut::<u32>(&["bindpose_start"]).unwrap() = u32::MAX;
+        instance.reset_change_detection();
+
+        let stats = instance.stats().unwrap();
+        println!("{stats}");
+        assert_eq!(stats.frames, 2);
+        assert_eq!(stats.bytes_marked, 12);
+        assert_eq!(stats.blocks_marked, 3);
+        assert_eq!(stats.runs, 2);
+        assert_eq!(stats.bytes_uploaded, 48);
+        assert_eq!(stats.bytes_changed, 5);
+        assert_eq!(stats.write_amplification(), 48.0 / 5.0);
+        // Fields sharing a block with a marked field are counted too
+        assert_eq!(
+            stats.top_fields(3),
+            vec![("aabb_min", 2), ("material_index", 2), ("aabb_max", 1)]
+        );
+        assert!(stats
+            .report(1)
+            .to_string()
+            .ends_with("Most marked fields:\n       2  aabb_min"));
+
+        // Aggregated over a batch
+        let mut other = instance.clone();
+        other.reset_stats();
+        *other.get_mut::<Mat4>(&["local_to_world"]).unwrap() = Mat4::ZERO;
+        *instance.get_mut::<u32>(&["material_index"]).unwrap() = 8;
+        let mut batch = RetainedUploadBatch::new(16);
+        batch.add(0, &mut instance);
+        batch.add(96, &mut other);
+        assert_eq!(batch.change_stats.frames, 2);
+        assert_eq!(batch.change_stats.bytes_marked, 68);
+        assert_eq!(batch.change_stats.bytes_uploaded, 80);
+        // The identity matrix has 4 floats of 1.0 (0x3f800000), each with 2 non zero bytes
+        assert_eq!(batch.change_stats.bytes_changed, 1 + 8);
+        assert_eq!(instance.stats().unwrap().frames, 3);
+        // Unchanged structs still record a frame
+        batch.add(0, &mut instance);
+        assert_eq!(batch.change_stats.frames, 3);
+        assert_eq!(batch.stats.changed_structs, 2);
+        assert_eq!(instance.stats().unwrap().frames, 4);
+
+        // Serving several consumers is still one frame
+        instance.set_consumer_count(2);
+        instance.reset_stats();
+        *instance.get_mut::<u32>(&["material_index"]).unwrap() = 9;
+        for consumer in 0..2 {
+            instance.retrieve_changes_for_and_ack::<u32>(consumer, |_, _, _| {});
+        }
+        instance.reset_change_detection();
+        let stats = instance.stats().unwrap();
+        assert_eq!((stats.frames, stats.runs), (1, 1));
+        assert_eq!(stats.field_counts["material_index"], 1);
+    }
+}
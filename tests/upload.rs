@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod tests {
+
+    use bytemuck::{Pod, Zeroable};
+    use dyn_pod_struct::{
+        base_type::BaseType,
+        dyn_layout::HasDynLayout,
+        dyn_layout_builder::DynLayoutBuilder,
+        packing::PackingRules,
+        tracked_dyn_struct::TrackedDynStruct,
+        upload_batch::{RetainedUploadBatch, UploadStats},
+        upload_plan::{UploadCosts, UploadPlan},
+    };
+    use dyn_pod_struct_derive::DynLayout;
+    use glam::{vec3, Mat4, Vec3};
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct InstanceData {
+        pub local_to_world: Mat4,
+        pub aabb_min: Vec3,
+        pub material_index: u32,
+        pub aabb_max: Vec3,
+        pub bindpose_start: u32,
+    }
+
+    const SIZE: usize = size_of::<InstanceData>();
+
+    fn expected(instances: &[TrackedDynStruct]) -> Vec<u8> {
+        instances
+            .iter()
+            .flat_map(|instance| instance.dyn_struct.data.iter().copied())
+            .collect()
+    }
+
+    #[test]
+    fn test_retained_upload_batch() {
+        let layout = InstanceData::dyn_layout();
+        let mut instances = (0..4)
+            .map(|i| {
+                let data = InstanceData {
+                    material_index: i,
+                    ..Default::default()
+                };
+                TrackedDynStruct::new(&data, &layout, 16, true)
+            })
+            .collect::<Vec<_>>();
+        let mut retained = vec![0; SIZE * instances.len()];
+
+        // Everything starts out changed so the first batch is a full upload
+        let mut batch = RetainedUploadBatch::new(16);
+        batch.extend(
+            instances
+                .iter_mut()
+                .enumerate()
+                .map(|(i, instance)| (i * SIZE, instance)),
+        );
+        assert_eq!(batch.len(), 4 * SIZE / 16);
+        assert_eq!(batch.stats.src_bytes, 4 * SIZE);
+        batch.apply(&mut retained);
+        assert_eq!(retained, expected(&instances));
+        assert!(instances.iter().all(|instance| !instance.changed()));
+
+        batch.reset();
+        assert!(batch.is_empty());
+        *instances[1].get_mut::<u32>(&["bindpose_start"]).unwrap() = 9;
+        *instances[3].get_mut::<Vec3>(&["aabb_min"]).unwrap() = vec3(1.0, 2.0, 3.0);
+        *instances[3].get_mut::<u32>(&["material_index"]).unwrap() = 5;
+        for (i, instance) in instances.iter_mut().enumerate() {
+            batch.add(i * SIZE, instance);
+        }
+        // bindpose_start is in the last block of instance 1, aabb_min & material_index share block 4 of instance 3
+        assert_eq!(batch.update_indices, vec![6 + 5, 3 * 6 + 4]);
+        assert_eq!(batch.stride_words(), 4);
+        assert_eq!(batch.src.len(), 2 * 4);
+        assert_eq!(
+            batch.stats,
+            UploadStats {
+                structs: 4,
+                changed_structs: 2,
+                tracked_bytes: 4 * SIZE,
+                src_bytes: 32,
+                index_bytes: 8,
+            }
+        );
+        assert_eq!(batch.stats.saved_bytes(), 4 * SIZE as isize - 40);
+        batch.apply(&mut retained);
+        assert_eq!(retained, expected(&instances));
+        let data = bytemuck::pod_read_unaligned::<InstanceData>(&retained[3 * SIZE..4 * SIZE]);
+        assert_eq!(data.aabb_min, vec3(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_upload_batch_stride_mismatch() {
+        let layout = InstanceData::dyn_layout();
+        let mut instance = TrackedDynStruct::new(&InstanceData::default(), &layout, 4, true);
+        RetainedUploadBatch::new(16).add(0, &mut instance);
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_upload_plan() {
+        let layout = DynLayoutBuilder::new("Values", PackingRules::Std430)
+            .with_array("values", BaseType::U32, 256)
+            .build();
+        let mut values = TrackedDynStruct::from_bytes(vec![0; 1024], layout, 4, false);
+        let costs = UploadCosts::default();
+        assert!(values.plan_upload(&costs).is_empty());
+
+        *values.get_mut_raw::<u32>(40) = 1;
+        assert_eq!(values.plan_upload(&costs), UploadPlan::Ranges(vec![40..44]));
+
+        // Almost everything changed, a full upload is as cheap and simpler
+        values.update_bitmask.set(0..128);
+        values.update_bitmask.set(130..256);
+        assert_eq!(values.plan_upload(&costs), UploadPlan::Full);
+        values.reset_change_detection();
+        let mut retained = vec![0; 1024];
+        retained.extend_from_slice(&values.dyn_struct.data);
+        let mut copied = retained.clone();
+
+        // Many small changes spread over the struct are cheapest to scatter when copies are expensive
+        for i in 0..32 {
+            *values.get_mut_raw::<u32>(i * 32) = i as u32 + 1;
+        }
+        let scatter_costs = UploadCosts {
+            per_copy: 64.0,
+            per_byte: 1.0,
+            per_dispatch: 0.0,
+        };
+        let estimate = scatter_costs.estimate(&values.update_bitmask, 4, 1024);
+        assert_eq!(estimate.scatter, 2.0 * 64.0 + 32.0 * 8.0);
+        assert_eq!(estimate.ranges, 64.0 + 996.0);
+        assert_eq!(estimate.full, 64.0 + 1024.0);
+        let plan = values.plan_upload(&scatter_costs);
+        assert_eq!(
+            plan,
+            UploadPlan::Scatter((0..32).map(|i| i * 32..i * 32 + 4).collect())
+        );
+        // With an expensive dispatch the same changes are merged into one range
+        assert_eq!(
+            values.plan_upload(&UploadCosts {
+                per_dispatch: 1000.0,
+                ..scatter_costs
+            }),
+            UploadPlan::Ranges(vec![0..996])
+        );
+
+        plan.for_each_copy(&values.dyn_struct.data, |offset, bytes| {
+            copied[1024 + offset..1024 + offset + bytes.len()].copy_from_slice(bytes)
+        });
+        let mut batch = RetainedUploadBatch::new(4);
+        batch.add_plan(1024, &mut values, &plan);
+        assert_eq!(batch.len(), 32);
+        assert!(!values.changed());
+        batch.apply(&mut retained);
+        assert_eq!(&retained[1024..], values.dyn_struct.data.as_slice());
+        assert_eq!(retained, copied);
+    }
+}
@@ -0,0 +1,266 @@
+#[cfg(test)]
+mod tests {
+
+    use bytemuck::{Pod, Zeroable};
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, FRAC_PI_8};
+
+    use dyn_pod_struct::{
+        base_type::BaseType,
+        blend::{BlendOptions, IntegerPolicy, MatrixPolicy},
+        dyn_layout::HasDynLayout,
+        dyn_struct::DynStruct,
+        dyn_value::SetValueError,
+        tracked_dyn_struct::TrackedDynStruct,
+        value_diff::{write_value_changes, DiffOptions},
+    };
+    use dyn_pod_struct_derive::DynLayout;
+    use glam::{vec2, vec3, vec4, Mat2, Mat4, Quat, Vec2, Vec3, Vec4};
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Light {
+        pub position: Vec3,
+        pub range: f32,
+        pub color: Vec4,
+        pub falloff: Mat2,
+        pub shadow_bias: f32,
+        pub layer: i32,
+        pub flags: u32,
+        pub intensity: f32,
+    }
+
+    #[test]
+    fn test_diff_values() {
+        let layout = Light::dyn_layout();
+        let preset = DynStruct::new(
+            &Light {
+                range: 10.0,
+                layer: -1,
+                intensity: f32::NAN,
+                ..Default::default()
+            },
+            &layout,
+        );
+        let mut current = preset.clone();
+        *current.get_mut::<Vec3>(&["position"]).unwrap() = vec3(0.1, 0.0, 2.0);
+        *current.get_mut::<f32>(&["range"]).unwrap() = 10.0001;
+        *current.get_mut::<Vec4>(&["color"]).unwrap() = vec4(1.0, 0.5, 0.0, 1.0);
+        *current.get_mut::<Mat2>(&["falloff"]).unwrap() = Mat2::ZERO;
+        *current.get_mut::<i32>(&["layer"]).unwrap() = -3;
+
+        let changes = preset.diff_values(&current);
+        let mut text = Vec::new();
+        write_value_changes(&mut text, &changes[3..]).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "-falloff: [(1, 0), (0, 1)]\n+falloff: [(0, 0), (0, 0)]\n-layer: -1\n+layer: -3\n"
+        );
+        let changes = changes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                "position: (0, 0, 0) -> (0.1, 0, 2)",
+                "range: 10 -> 10.0001",
+                "color: (0, 0, 0, 0) -> (1, 0.5, 0, 1)",
+                "falloff: [(1, 0), (0, 1)] -> [(0, 0), (0, 0)]",
+                "layer: -1 -> -3",
+            ]
+        );
+
+        let options = DiffOptions {
+            epsilon: 0.001,
+            nan_equal: false,
+        };
+        let paths = preset
+            .diff_values_with(&current, &options)
+            .into_iter()
+            .map(|c| c.path)
+            .collect::<Vec<_>>();
+        // NaN is never equal to itself without nan_equal, even though the bytes are the same
+        assert_eq!(
+            paths,
+            vec!["position", "color", "falloff", "layer", "intensity"]
+        );
+        assert!(preset.diff_values(&preset).is_empty());
+        assert_eq!(
+            preset.value(&["layer"]).unwrap().to_string(),
+            "-1".to_string()
+        );
+    }
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Animated {
+        pub transform: Mat4,
+        pub rotation: Quat,
+        pub color: Vec3,
+        pub frame: u32,
+        pub layer: i32,
+        pub mode: u32,
+        pub weight: f32,
+        pub flags: u32,
+    }
+
+    #[test]
+    fn test_lerp() {
+        let layout = Animated::dyn_layout();
+        let a = Animated {
+            transform: Mat4::from_translation(vec3(0.0, 0.0, 0.0)),
+            rotation: Quat::IDENTITY,
+            color: vec3(0.0, 0.0, 1.0),
+            frame: 0,
+            layer: -4,
+            mode: 1,
+            weight: 0.0,
+            flags: 7,
+        };
+        let b = Animated {
+            transform: Mat4::from_rotation_z(FRAC_PI_2)
+                * Mat4::from_translation(vec3(4.0, 0.0, 0.0)),
+            rotation: Quat::from_rotation_y(FRAC_PI_2),
+            color: vec3(1.0, 0.0, 1.0),
+            frame: 10,
+            layer: 4,
+            mode: 2,
+            weight: 2.0,
+            flags: 7,
+        };
+        let a = DynStruct::new(&a, &layout);
+        let b = DynStruct::new(&b, &layout);
+
+        let mid = DynStruct::lerp(&a, &b, 0.5);
+        let get = |s: &DynStruct| *bytemuck::from_bytes::<Animated>(&s.data);
+        let value = get(&mid);
+        assert_eq!(value.color, vec3(0.5, 0.0, 1.0));
+        assert_eq!(value.weight, 1.0);
+        assert!(value
+            .rotation
+            .abs_diff_eq(Quat::from_rotation_y(FRAC_PI_4), 1e-6));
+        // Integers step at 0.5 by default
+        assert_eq!((value.frame, value.layer, value.mode), (10, 4, 2));
+        assert_eq!(DynStruct::lerp(&a, &b, 0.0).data, a.data);
+        assert_eq!(DynStruct::lerp(&a, &b, 1.0).data, b.data);
+
+        let options = BlendOptions {
+            integers: IntegerPolicy::Round,
+            matrices: MatrixPolicy::Decompose,
+            ..Default::default()
+        }
+        .with_field_integers("mode", IntegerPolicy::KeepA);
+        let value = get(&DynStruct::lerp_with(&a, &b, 0.25, &options));
+        assert_eq!((value.frame, value.layer, value.mode), (3, -2, 1));
+        // Decomposing keeps the transform rigid, blending component-wise doesn't
+        let expected =
+            Mat4::from_rotation_translation(Quat::from_rotation_z(FRAC_PI_8), vec3(0.0, 1.0, 0.0));
+        assert!(value.transform.abs_diff_eq(expected, 1e-5));
+        let component_wise = get(&DynStruct::lerp(&a, &b, 0.25)).transform;
+        assert!(!component_wise.abs_diff_eq(expected, 1e-3));
+
+        // Only fields whose bytes change are marked
+        let mut tracked = TrackedDynStruct::from_bytes(a.data.clone(), layout.clone(), 4, false);
+        assert!(tracked.blend_into(&b, 0.25, &BlendOptions::default()));
+        let changed = tracked
+            .changed_fields()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert!(changed.contains(&"color") && changed.contains(&"weight"));
+        assert!(!changed.contains(&"frame") && !changed.contains(&"flags"));
+        tracked.reset_change_detection();
+        let target = tracked.dyn_struct.clone();
+        assert!(!tracked.blend_into(&target, 0.5, &BlendOptions::default()));
+        assert!(!tracked.changed());
+    }
+
+    #[repr(C)]
+    #[derive(DynLayout, Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+    pub struct Scene {
+        pub light: Light,
+        pub material_index: u32,
+        pub exposure: f32,
+        pub tint: Vec2,
+    }
+
+    #[test]
+    fn test_set_from_str() {
+        let layout = Scene::dyn_layout();
+        let mut scene = DynStruct::new(&Scene::default(), &layout);
+
+        scene.set_from_str(&["material_index"], "7").unwrap();
+        scene.set_from_str(&["light", "layer"], " -3 ").unwrap();
+        scene.set_from_str(&["light", "position"], "1,2,3").unwrap();
+        scene
+            .set_from_str(&["light", "color"], "(1, 0.5, 0, 1)")
+            .unwrap();
+        scene
+            .set_from_str(&["light", "falloff"], "[(1, 2), (3, 4)]")
+            .unwrap();
+        scene.set_from_str(&["tint"], "0.25, 1e1").unwrap();
+        let value = *bytemuck::from_bytes::<Scene>(&scene.data);
+        assert_eq!(value.material_index, 7);
+        assert_eq!(value.light.layer, -3);
+        assert_eq!(value.light.position, vec3(1.0, 2.0, 3.0));
+        assert_eq!(value.light.color, vec4(1.0, 0.5, 0.0, 1.0));
+        assert_eq!(
+            value.light.falloff,
+            Mat2::from_cols_array(&[1.0, 2.0, 3.0, 4.0])
+        );
+        assert_eq!(value.tint, vec2(0.25, 10.0));
+
+        // get_as_string output can be parsed back
+        let falloff = scene.get_as_string(&["light", "falloff"]).unwrap();
+        assert_eq!(falloff, "[(1, 2), (3, 4)]");
+        let mut copy = DynStruct::new(&Scene::default(), &layout);
+        copy.set_from_str(&["light", "falloff"], &falloff).unwrap();
+        assert_eq!(
+            copy.get::<Mat2>(&["light", "falloff"]),
+            scene.get(&["light", "falloff"])
+        );
+        assert_eq!(scene.get_as_string(&["tint"]).unwrap(), "(0.25, 10)");
+        assert!(scene
+            .get_as_string(&["light"])
+            .unwrap()
+            .starts_with("{ position: (1, 2, 3), range: 0, color: (1, 0.5, 0, 1), "));
+        assert_eq!(scene.get_as_string(&["missing"]), None);
+
+        let err = scene.set_from_str(&["material_index"], "-1").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "can't set `material_index` (u32) to \"-1\": -1 is out of range"
+        );
+        let err = scene
+            .set_from_str(&["light", "layer"], "2147483648")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            SetValueError::Parse {
+                expected: BaseType::I32,
+                ..
+            }
+        ));
+        assert!(scene.set_from_str(&["light", "layer"], "1.5").is_err());
+        assert!(scene.set_from_str(&["exposure"], "1e39").is_err());
+        assert!(scene.set_from_str(&["light", "position"], "1, 2").is_err());
+        assert!(scene
+            .set_from_str(&["light", "position"], "(1, 2, 3")
+            .is_err());
+        assert_eq!(
+            scene
+                .set_from_str(&["light", "size"], "1")
+                .unwrap_err()
+                .to_string(),
+            "no field `size` in `light`"
+        );
+        assert!(scene.set_from_str(&["light"], "1").is_err());
+        // Nothing was written by the failed sets
+        assert_eq!(*bytemuck::from_bytes::<Scene>(&scene.data), value);
+
+        let mut tracked = TrackedDynStruct::from_bytes(scene.data.clone(), layout, 4, false);
+        assert!(!tracked.set_from_str(&["material_index"], "7").unwrap());
+        assert!(tracked.set_from_str(&["light", "flags"], "255").unwrap());
+        let changed = tracked
+            .changed_fields()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert_eq!(changed, vec!["light.flags"]);
+    }
+}